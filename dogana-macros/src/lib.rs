@@ -0,0 +1,290 @@
+//! # Dogana macros
+//! The procedural macros of Dogana. They are re-exported by the `dogana` crate, which should be
+//! used instead of this one.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse::Parser, punctuated::Punctuated, Error, Expr, ExprLit, Ident, ItemFn, Lit, MetaNameValue,
+    ReturnType, Token, Visibility,
+};
+
+/// Turn a function returning a test definition (e.g. a `DoganaTestBuilder` or a `ConsoleTest`)
+/// into a test which builds and runs it.
+///
+/// The test is named after the module path and the name of the function, thus its container
+/// and its snapshots have a stable name. The attribute accepts:
+/// - `image`: the metadata key of the image variant (e.g. `debian`), or a list of keys (e.g.
+///   `[debian, alpine]`). With a list, a module named after the function contains a test for each
+///   variant. When it is omitted, the image of the definition is used.
+/// - `timeout`: the maximum duration of the test container (e.g. `"30s"`).
+///
+/// The other attributes of the function (e.g. `#[ignore]`) are applied to the generated tests.
+///
+/// ```ignore
+/// #[dogana::test(image = [debian, alpine], timeout = "30s")]
+/// fn echo_prints_its_arguments() -> DoganaTestBuilder {
+///     let mut builder = DoganaTestBuilder::new();
+///     builder
+///         .set_run_commands(&["echo \"test\""])
+///         .set_expected_output("test");
+///     builder
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    expand(args.into(), item.into())
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// The arguments of the test attribute.
+#[derive(Default)]
+struct TestArgs {
+    images: Vec<Ident>,
+    /// The timeout, in milliseconds.
+    timeout: Option<u64>,
+}
+
+impl TestArgs {
+    fn parse(args: TokenStream2) -> syn::Result<Self> {
+        let mut test_args = TestArgs::default();
+        for arg in Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse2(args)? {
+            if arg.path.is_ident("image") {
+                test_args.images = image_keys(&arg.value)?;
+            } else if arg.path.is_ident("timeout") {
+                match arg.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(timeout),
+                        ..
+                    }) => {
+                        test_args.timeout = Some(duration_millis(&timeout.value()).ok_or_else(
+                            || {
+                                Error::new(
+                                    timeout.span(),
+                                    "invalid duration, expected an integer and a unit among `ms`, `s`, `m` and `h`",
+                                )
+                            },
+                        )?)
+                    }
+                    other => {
+                        return Err(Error::new_spanned(
+                            other,
+                            "expected a duration literal, e.g. \"30s\"",
+                        ))
+                    }
+                }
+            } else {
+                return Err(Error::new_spanned(
+                    arg.path,
+                    "unknown argument, expected `image` or `timeout`",
+                ));
+            }
+        }
+        Ok(test_args)
+    }
+}
+
+/// Parse a duration made of an integer and a unit among `ms`, `s`, `m` and `h` (e.g. `30s`) into
+/// milliseconds, as `dogana::dogana_test::test_options::parse_duration` does.
+fn duration_millis(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let unit_index = text.find(|c: char| !c.is_ascii_digit())?;
+    let value = text[..unit_index].parse::<u64>().ok()?;
+    let unit_millis = match &text[unit_index..] {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        _ => return None,
+    };
+    value.checked_mul(unit_millis)
+}
+
+/// The image keys of the `image` argument, which is a key or a non-empty list of keys.
+fn image_keys(value: &Expr) -> syn::Result<Vec<Ident>> {
+    match value {
+        Expr::Array(array) if array.elems.is_empty() => Err(Error::new_spanned(
+            array,
+            "expected at least an image variant",
+        )),
+        Expr::Array(array) => array.elems.iter().map(image_key).collect(),
+        other => Ok(vec![image_key(other)?]),
+    }
+}
+
+fn image_key(value: &Expr) -> syn::Result<Ident> {
+    match value {
+        Expr::Path(path) => path.path.get_ident().cloned(),
+        _ => None,
+    }
+    .ok_or_else(|| Error::new_spanned(value, "expected an image variant, e.g. `debian`"))
+}
+
+fn expand(args: TokenStream2, item: TokenStream2) -> syn::Result<TokenStream2> {
+    let args = TestArgs::parse(args)?;
+    let mut definition = syn::parse2::<ItemFn>(item)?;
+    let signature = &definition.sig;
+    if let Some(asyncness) = signature.asyncness {
+        return Err(Error::new_spanned(
+            asyncness,
+            "a dogana test cannot be async",
+        ));
+    }
+    if !signature.inputs.is_empty() {
+        return Err(Error::new_spanned(
+            &signature.inputs,
+            "a dogana test cannot have arguments",
+        ));
+    }
+    if let ReturnType::Default = signature.output {
+        return Err(Error::new_spanned(
+            signature,
+            "a dogana test must return its definition, e.g. a DoganaTestBuilder",
+        ));
+    }
+    let name = definition.sig.ident.clone();
+    let visibility = std::mem::replace(&mut definition.vis, Visibility::Inherited);
+    let attributes = std::mem::take(&mut definition.attrs);
+    let timeout = match &args.timeout {
+        Some(timeout) => quote!(::core::option::Option::Some(
+            ::core::time::Duration::from_millis(#timeout)
+        )),
+        None => quote!(::core::option::Option::None),
+    };
+    // The definition is nested in each test, where it shadows the test function.
+    let test_function = |test_name: &Ident, image: Option<&Ident>| {
+        let image = match image {
+            Some(key) => {
+                let image = format_ident!(
+                    "{}_IMAGE",
+                    key.to_string().to_uppercase(),
+                    span = key.span()
+                );
+                quote!(::core::option::Option::Some(&::dogana::dogana_images::#image))
+            }
+            None => quote!(::core::option::Option::None),
+        };
+        quote! {
+            #(#attributes)*
+            #[::core::prelude::v1::test]
+            #visibility fn #test_name() -> ::dogana::dogana_test::DoganaTestResult {
+                #definition
+                ::dogana::dogana_test::test_definition::run_test_definition(
+                    #name(),
+                    ::core::concat!(::core::module_path!(), "::", ::core::stringify!(#test_name)),
+                    #image,
+                    #timeout,
+                )
+            }
+        }
+    };
+    Ok(match args.images.as_slice() {
+        [] => test_function(&name, None),
+        [image] => test_function(&name, Some(image)),
+        images => {
+            let tests = images.iter().map(|it| test_function(it, Some(it)));
+            quote! {
+                #visibility mod #name {
+                    #[allow(unused_imports)]
+                    use super::*;
+
+                    #(#tests)*
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, quote, TestArgs, TokenStream2};
+
+    fn expand_error(args: TokenStream2, item: TokenStream2) -> String {
+        expand(args, item).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        let args = TestArgs::parse(quote!(image = [debian, alpine], timeout = "30s")).unwrap();
+        assert_eq!(args.images, ["debian", "alpine"]);
+        assert_eq!(args.timeout, Some(30_000));
+        let args = TestArgs::parse(quote!(image = debian)).unwrap();
+        assert_eq!(args.images, ["debian"]);
+        assert!(args.timeout.is_none());
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        let item = quote!(
+            fn test() -> DoganaTestBuilder {
+                todo!()
+            }
+        );
+        assert_eq!(
+            expand_error(quote!(images = debian), item.clone()),
+            "unknown argument, expected `image` or `timeout`"
+        );
+        assert_eq!(
+            expand_error(quote!(image = []), item.clone()),
+            "expected at least an image variant"
+        );
+        assert_eq!(
+            expand_error(quote!(timeout = 30), item.clone()),
+            "expected a duration literal, e.g. \"30s\""
+        );
+        assert_eq!(
+            expand_error(quote!(timeout = "30x"), item),
+            "invalid duration, expected an integer and a unit among `ms`, `s`, `m` and `h`"
+        );
+    }
+
+    #[test]
+    fn invalid_functions_are_rejected() {
+        assert_eq!(
+            expand_error(
+                quote!(),
+                quote!(
+                    fn test() {}
+                )
+            ),
+            "a dogana test must return its definition, e.g. a DoganaTestBuilder"
+        );
+        assert_eq!(
+            expand_error(
+                quote!(),
+                quote!(
+                    fn test(x: u8) -> DoganaTestBuilder {
+                        todo!()
+                    }
+                )
+            ),
+            "a dogana test cannot have arguments"
+        );
+    }
+
+    #[test]
+    fn a_test_is_generated_for_each_variant() {
+        let output = expand(
+            quote!(image = [debian, alpine]),
+            quote!(
+                #[ignore]
+                fn echo() -> DoganaTestBuilder {
+                    todo!()
+                }
+            ),
+        )
+        .unwrap()
+        .to_string();
+        assert!(output.starts_with("mod echo"), "{}", output);
+        assert!(output.contains("fn debian ()"), "{}", output);
+        assert!(output.contains("fn alpine ()"), "{}", output);
+        assert!(
+            output.contains(":: dogana :: dogana_images :: ALPINE_IMAGE"),
+            "{}",
+            output
+        );
+        assert_eq!(output.matches("# [ignore]").count(), 2, "{}", output);
+    }
+}
@@ -0,0 +1,563 @@
+//! Tests written as console sessions, in `.console` files or in the `console` code blocks of
+//! Markdown files (in the style of `trycmd`).
+//!
+//! A console session is made of commands, each followed by its expected output:
+//! ```text
+//! $ mycli --version
+//! mycli [..]
+//! $ mycli --unknown
+//! ? 2
+//! error: unexpected argument '--unknown'
+//! ...
+//! ```
+//! - A command starts with `$ ` and can continue on the following lines starting with `> `.
+//! - A `? <code>` line right after the command sets its expected exit code, which otherwise is
+//!   `0`; `? failed` accepts any error exit code.
+//! - The output contains both stdout and stderr, and it can use the wildcards of
+//!   [OutputMatcher::Wildcard].
+//! - The commands are run by the same shell, thus `exit` ends the whole session: to check an
+//!   exit code, run the command in a child shell (e.g. `sh -c '...; exit 3'`).
+//!
+//! The file can start with a header which selects the image and the options of the test. The
+//! header is delimited by `---` lines and it is made of `key = value` lines:
+//! ```text
+//! ---
+//! image = alpine
+//! timeout = 30s
+//! ---
+//! $ mycli --help
+//! ...
+//! ```
+//! The supported keys are:
+//! - `image`: the metadata key of the image variant (e.g. `alpine`), defaults to `debian`
+//! - `shell`: `sh` or `bash`
+//! - `user`: the user running the commands
+//! - `working_dir`: the directory where the commands start
+//! - `timeout`: the maximum duration of the test (e.g. `30s`)
+//! - `network`: `none`, `bridge` or the name of a network
+//! - `env`: an environment variable, as `KEY=VALUE`, which can be repeated
+//! - `init`: an init command, which can be repeated
+//!
+//! Each file is run as a [DoganaTest](crate::dogana_test::DoganaTest) in transcript mode. The
+//! files of a directory can be run by an integration test with `harness = false` through
+//! [harness], so that each file is reported as a test:
+//! ```ignore
+//! fn main() -> std::process::ExitCode {
+//!     dogana::console_test::harness("tests/console")
+//! }
+//! ```
+//!
+//! The shell examples of the documentation (e.g. the README) can be checked as well, through
+//! [ConsoleTest::from_doc_examples]:
+//! ```ignore
+//! #[test]
+//! fn readme_examples() -> Result<(), Box<dyn std::error::Error>> {
+//!     Ok(ConsoleTest::from_doc_examples(Path::new("README.md"), "readme")?
+//!         .set_image(&ALPINE_IMAGE)
+//!         .run()?)
+//! }
+//! ```
+
+mod header;
+mod markdown;
+
+use std::{
+    error::Error,
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{Arc, LazyLock},
+};
+
+use header::ConsoleTestHeader;
+use hierrorchy::{error_leaf, error_node};
+use libtest_mimic::{Arguments, Trial};
+use markdown::{fenced_blocks, FencedBlock};
+
+use crate::{
+    dogana_test::{
+        accept_exit_code::AcceptExitCode, builder::DoganaTestBuilder,
+        output_matcher::OutputMatcher, transcript::TranscriptStep, DoganaTestResult,
+    },
+    image_name::ImageName,
+};
+
+/// A line of a file, with its number.
+pub(crate) type NumberedLine<'a> = (usize, &'a str);
+
+const CONSOLE_EXTENSION: &str = "console";
+const MARKDOWN_EXTENSION: &str = "md";
+const CONSOLE_LANGUAGE: &str = "console";
+const SHELL_LANGUAGES: [&str; 2] = ["sh", "shell"];
+/// The annotations of the code blocks which must not be run.
+const OPT_OUT_ANNOTATIONS: [&str; 2] = ["ignore", "no_run"];
+
+/// A test read from a console session file.
+#[derive(Debug, Clone)]
+pub struct ConsoleTest {
+    name: String,
+    header: ConsoleTestHeader,
+    steps: Vec<TranscriptStep>,
+}
+
+impl ConsoleTest {
+    /// Read a `.console` or a `.md` file. The name identifies the test, e.g. in the test
+    /// harness output.
+    pub fn from_file(path: &Path, name: &str) -> Result<Self, ConsoleTestError> {
+        let content = fs::read_to_string(path)?;
+        let parsed = if has_extension(path, MARKDOWN_EXTENSION) {
+            Self::parse_markdown(name, &content)
+        } else {
+            Self::parse_console(name, &content)
+        };
+        Ok(parsed.map_err(|(line, reason)| {
+            ConsoleTestParseError::new(path.to_path_buf(), line, reason)
+        })?)
+    }
+
+    /// Parse a console session, made of the whole content.
+    fn parse_console(name: &str, content: &str) -> Result<Self, (usize, String)> {
+        let lines = numbered_lines(content);
+        let (header, session) = ConsoleTestHeader::parse(&lines)?;
+        Ok(ConsoleTest {
+            name: name.to_owned(),
+            header,
+            steps: parse_session(session)?,
+        })
+    }
+
+    /// Parse the `console` code blocks of a Markdown file, which are run as a single session.
+    fn parse_markdown(name: &str, content: &str) -> Result<Self, (usize, String)> {
+        let lines = numbered_lines(content);
+        let (header, body) = ConsoleTestHeader::parse(&lines)?;
+        let mut steps = vec![];
+        for block in fenced_blocks(body).iter().filter(|it| is_tested(it)) {
+            if block.language() == CONSOLE_LANGUAGE {
+                steps.extend(parse_session(&block.content)?);
+            }
+        }
+        Ok(ConsoleTest {
+            name: name.to_owned(),
+            header,
+            steps,
+        })
+    }
+
+    /// Read the shell examples of a Markdown file which is not written as a test, e.g. the
+    /// README, so that they can be checked.
+    ///
+    /// The `console` code blocks are run as in a console test, comparing the shown output. The
+    /// `sh` and `shell` code blocks are run as a single command each, whose output is not
+    /// checked; the block fails if its last command fails. The blocks annotated with `ignore` or
+    /// `no_run` (e.g. ` ```console,ignore `) are not run.
+    ///
+    /// The file has no header, thus the test runs in the Debian image unless another one is set
+    /// with [ConsoleTest::set_image].
+    pub fn from_doc_examples(path: &Path, name: &str) -> Result<Self, ConsoleTestError> {
+        let content = fs::read_to_string(path)?;
+        Ok(
+            Self::parse_doc_examples(name, &content).map_err(|(line, reason)| {
+                ConsoleTestParseError::new(path.to_path_buf(), line, reason)
+            })?,
+        )
+    }
+
+    fn parse_doc_examples(name: &str, content: &str) -> Result<Self, (usize, String)> {
+        let lines = numbered_lines(content);
+        let mut steps = vec![];
+        for block in fenced_blocks(&lines).iter().filter(|it| is_tested(it)) {
+            let language = block.language();
+            // Shell examples are sometimes written as console sessions.
+            let is_session = language == CONSOLE_LANGUAGE
+                || (SHELL_LANGUAGES.contains(&language)
+                    && block.content.iter().any(|(_, it)| it.starts_with("$ ")));
+            if is_session {
+                steps.extend(parse_session(&block.content)?);
+            } else if SHELL_LANGUAGES.contains(&language) {
+                steps.push(TranscriptStep {
+                    command: block
+                        .content
+                        .iter()
+                        .map(|(_, it)| *it)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    expected_output: None,
+                    expected_stderr: None,
+                    accepted_exit_codes: AcceptExitCode::Success,
+                });
+            }
+        }
+        Ok(ConsoleTest {
+            name: name.to_owned(),
+            header: ConsoleTestHeader::default(),
+            steps,
+        })
+    }
+
+    /// Set the image of the test, e.g. one of [dogana_images](crate::dogana_images), overriding
+    /// the one of the file header.
+    pub fn set_image(&mut self, image: &'static LazyLock<Arc<ImageName>>) -> &mut Self {
+        self.header.image = image;
+        self
+    }
+
+    /// The name of the test.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Create a builder for the test, which can be further customized before building it.
+    pub fn builder(&self) -> DoganaTestBuilder {
+        let mut builder = DoganaTestBuilder::new();
+        builder
+            .set_test_name(&self.name)
+            .set_test_options(self.header.test_options.clone())
+            .set_base_image(self.header.image)
+            .set_init_commands(
+                &self
+                    .header
+                    .init_commands
+                    .iter()
+                    .map(|it| it.as_str())
+                    .collect::<Vec<_>>(),
+            );
+        for (key, value) in &self.header.env {
+            builder.set_env(key, value);
+        }
+        for step in &self.steps {
+            builder.add_transcript_step(step.clone());
+        }
+        builder
+    }
+
+    /// Run the test.
+    pub fn run(&self) -> DoganaTestResult {
+        self.builder().build().run()
+    }
+}
+
+/// Find the console tests in the directory and its subdirectories, i.e. the `.console` files and
+/// the `.md` files containing `console` code blocks. Each test is named after the path of its
+/// file, relative to the directory.
+pub fn discover(dir: &Path) -> Result<Vec<ConsoleTest>, ConsoleTestError> {
+    let mut paths = vec![];
+    collect_files(dir, &mut paths)?;
+    paths.sort();
+    let mut tests = vec![];
+    for path in paths {
+        let name = path
+            .strip_prefix(dir)
+            .expect("the files are in the directory")
+            .to_string_lossy()
+            .into_owned();
+        let test = ConsoleTest::from_file(&path, &name)?;
+        if !test.steps.is_empty() {
+            tests.push(test);
+        }
+    }
+    Ok(tests)
+}
+
+/// Run the console tests of the directory as a test harness, reporting each file as a test. It
+/// is meant to be the `main` function of an integration test with `harness = false`, thus it
+/// accepts the arguments of the `cargo test` harness (e.g. the test name filter).
+///
+/// The directory is relative to the package root, where `cargo test` runs the tests.
+pub fn harness(dir: impl AsRef<Path>) -> ExitCode {
+    let arguments = Arguments::from_args();
+    let tests = match discover(dir.as_ref()) {
+        Ok(tests) => tests,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let trials = tests
+        .into_iter()
+        .map(|test| Trial::test(test.name().to_owned(), move || Ok(test.run()?)))
+        .collect();
+    libtest_mimic::run(&arguments, trials).exit_code()
+}
+
+/// Parse the numbered lines of a console session into transcript steps.
+pub(crate) fn parse_session(
+    lines: &[NumberedLine],
+) -> Result<Vec<TranscriptStep>, (usize, String)> {
+    let mut steps = vec![];
+    let mut current: Option<SessionStep> = None;
+    for (number, line) in lines {
+        if let Some(command) = line.strip_prefix("$ ").or((*line == "$").then_some("")) {
+            steps.extend(current.take().map(SessionStep::into_transcript_step));
+            current = Some(SessionStep {
+                command: command.to_owned(),
+                accepted_exit_codes: AcceptExitCode::Success,
+                output: vec![],
+            });
+            continue;
+        }
+        let Some(step) = current.as_mut() else {
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Err((*number, "expected a command, starting with `$ `".to_owned()));
+        };
+        // The continuation and the exit code lines are only allowed before the output.
+        if step.output.is_empty() {
+            if let Some(continuation) = line.strip_prefix("> ") {
+                step.command.push('\n');
+                step.command.push_str(continuation);
+                continue;
+            }
+            if let Some(exit_code) = line.strip_prefix("? ") {
+                step.accepted_exit_codes = match exit_code.trim() {
+                    "success" => AcceptExitCode::Success,
+                    "failed" => AcceptExitCode::Error,
+                    code => AcceptExitCode::Specific(
+                        code.parse()
+                            .map_err(|_| (*number, format!("invalid exit code `{}`", code)))?,
+                    ),
+                };
+                continue;
+            }
+        }
+        step.output.push(line);
+    }
+    steps.extend(current.map(SessionStep::into_transcript_step));
+    Ok(steps)
+}
+
+/// A command of a console session being parsed.
+struct SessionStep<'a> {
+    command: String,
+    accepted_exit_codes: AcceptExitCode,
+    output: Vec<&'a str>,
+}
+
+impl SessionStep<'_> {
+    fn into_transcript_step(mut self) -> TranscriptStep {
+        // Empty lines separating the commands are not part of the output.
+        while self.output.last().is_some_and(|it| it.trim().is_empty()) {
+            self.output.pop();
+        }
+        TranscriptStep {
+            // A console shows both stdout and stderr.
+            command: format!("{{ {}\n}} 2>&1", self.command),
+            expected_output: Some(OutputMatcher::wildcard(&self.output.join("\n"))),
+            expected_stderr: None,
+            accepted_exit_codes: self.accepted_exit_codes,
+        }
+    }
+}
+
+/// Whether the code block is not opted out of the tests.
+fn is_tested(block: &FencedBlock) -> bool {
+    !block
+        .annotations()
+        .any(|it| OPT_OUT_ANNOTATIONS.contains(&it))
+}
+
+fn numbered_lines(content: &str) -> Vec<NumberedLine<'_>> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, it)| (i + 1, it))
+        .collect()
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|it| it == extension)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), IoError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if has_extension(&path, CONSOLE_EXTENSION)
+            || has_extension(&path, MARKDOWN_EXTENSION)
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[error_leaf(format!("{}:{}: {}", self.path.display(), self.line, self.reason))]
+pub struct ConsoleTestParseError {
+    path: PathBuf,
+    line: usize,
+    reason: String,
+}
+
+impl ConsoleTestParseError {
+    pub fn new(path: PathBuf, line: usize, reason: String) -> Self {
+        ConsoleTestParseError { path, line, reason }
+    }
+
+    /// The path of the invalid file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The number of the invalid line.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Why the line is invalid.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+error_node! {
+    pub type ConsoleTestError<ConsoleTestParseError, IoError> = "failed to read console test"
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::dogana_test::transcript::{check_transcript, split_commands};
+
+    use super::*;
+
+    #[test]
+    fn session_is_parsed_into_steps() {
+        let test = ConsoleTest::parse_console(
+            "test",
+            indoc! {"
+                $ echo hello
+                hello
+
+                $ printf 'a\\n'
+                > printf 'b\\n'
+                a
+                b
+                $ false
+                ? 1
+            "},
+        )
+        .unwrap();
+        let commands = test
+            .steps
+            .iter()
+            .map(|it| it.command.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            commands,
+            [
+                "{ echo hello\n} 2>&1",
+                "{ printf 'a\\n'\nprintf 'b\\n'\n} 2>&1",
+                "{ false\n} 2>&1"
+            ]
+        );
+        let expected_output = test.steps[0].expected_output.as_ref().unwrap();
+        assert!(expected_output.check("hello").is_ok());
+        assert!(test.steps[2]
+            .accepted_exit_codes
+            .accepts(crate::dogana_test::exit_status::ExitStatus::Exited(1)));
+    }
+
+    #[test]
+    fn markdown_console_blocks_are_a_session() {
+        let test = ConsoleTest::parse_markdown(
+            "test",
+            indoc! {"
+                ---
+                image = alpine
+                ---
+                # Usage
+                ```console
+                $ echo one
+                one
+                ```
+                ```rust
+                fn main() {}
+                ```
+                ```console
+                $ echo two
+                two
+                ```
+            "},
+        )
+        .unwrap();
+        assert_eq!(test.steps.len(), 2);
+    }
+
+    #[test]
+    fn doc_examples_skip_opted_out_blocks() {
+        let test = ConsoleTest::parse_doc_examples(
+            "readme",
+            indoc! {"
+                ---
+                title: not a test header
+                ---
+                ```sh
+                cargo add --dev dogana
+                ```
+                ```console,ignore
+                $ mycli --interactive
+                ```
+                ```sh no_run
+                mycli --delete-everything
+                ```
+                ```shell
+                $ echo hello
+                hello
+                ```
+                ```rust
+                fn main() {}
+                ```
+            "},
+        )
+        .unwrap();
+        let commands = test
+            .steps
+            .iter()
+            .map(|it| (it.command.as_str(), it.expected_output.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            commands,
+            [
+                ("cargo add --dev dogana", false),
+                ("{ echo hello\n} 2>&1", true)
+            ]
+        );
+    }
+
+    #[test]
+    fn output_before_commands_is_an_error() {
+        let (line, reason) = ConsoleTest::parse_console("test", "\noutput\n$ true").unwrap_err();
+        assert_eq!(line, 2);
+        assert_eq!(reason, "expected a command, starting with `$ `");
+    }
+
+    #[test]
+    fn shipped_session_passes_with_local_shell() {
+        let test = ConsoleTest::from_file(Path::new("tests/console/echo.console"), "echo").unwrap();
+        let script = test
+            .steps
+            .iter()
+            .map(|it| it.script(true))
+            .collect::<String>();
+        let output = std::process::Command::new("sh")
+            .args(["-c", &script])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let joined_lines = |it: &[u8]| {
+            String::from_utf8_lossy(it)
+                .lines()
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let commands = split_commands(
+            &test.steps,
+            &joined_lines(&output.stdout),
+            &joined_lines(&output.stderr),
+        );
+        assert_eq!(check_transcript(&test.steps, &commands), None);
+    }
+}
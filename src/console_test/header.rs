@@ -0,0 +1,163 @@
+use std::sync::{Arc, LazyLock};
+
+use super::NumberedLine;
+use crate::{
+    dogana_images::{image_by_key, DEBIAN_IMAGE},
+    dogana_test::test_options::{parse_duration, DoganaTestOptions, NetworkMode, Shell},
+    image_name::ImageName,
+};
+
+/// The line which opens and closes the header of a console test file.
+const HEADER_FENCE: &str = "---";
+
+/// The settings of a console test file, written in its header.
+///
+/// The header is an optional block at the start of the file, delimited by `---` lines, made of
+/// `key = value` lines. Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone)]
+pub(crate) struct ConsoleTestHeader {
+    pub image: &'static LazyLock<Arc<ImageName>>,
+    pub init_commands: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub test_options: DoganaTestOptions,
+}
+
+impl Default for ConsoleTestHeader {
+    fn default() -> Self {
+        ConsoleTestHeader {
+            image: &DEBIAN_IMAGE,
+            init_commands: vec![],
+            env: vec![],
+            test_options: DoganaTestOptions::default(),
+        }
+    }
+}
+
+impl ConsoleTestHeader {
+    /// Parse the header at the start of the numbered lines, if any, returning it with the lines
+    /// which follow it. An error contains the line number and the reason of the failure.
+    pub fn parse<'a, 'b>(
+        lines: &'b [NumberedLine<'a>],
+    ) -> Result<(Self, &'b [NumberedLine<'a>]), (usize, String)> {
+        let mut header = ConsoleTestHeader::default();
+        if lines.first().map(|(_, it)| it.trim_end()) != Some(HEADER_FENCE) {
+            return Ok((header, lines));
+        }
+        for (index, (number, line)) in lines.iter().enumerate().skip(1) {
+            let line = line.trim();
+            if line == HEADER_FENCE {
+                return Ok((header, &lines[index + 1..]));
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err((*number, "expected a `key = value` line".to_owned()));
+            };
+            header
+                .set(key.trim(), unquote(value.trim()))
+                .map_err(|reason| (*number, reason))?;
+        }
+        Err((lines[0].0, "the header is not closed".to_owned()))
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let options = &mut self.test_options;
+        match key {
+            "image" => {
+                self.image =
+                    image_by_key(value).ok_or_else(|| format!("unknown image `{}`", value))?
+            }
+            "shell" => {
+                options.shell = match value {
+                    "sh" => Shell::Sh,
+                    "bash" => Shell::Bash,
+                    _ => return Err(format!("unknown shell `{}`", value)),
+                }
+            }
+            "user" => options.user = Some(value.to_owned()),
+            "working_dir" => options.working_dir = Some(value.to_owned()),
+            "timeout" => {
+                options.timeout = Some(
+                    parse_duration(value).ok_or_else(|| format!("invalid duration `{}`", value))?,
+                )
+            }
+            "network" => {
+                options.network = match value {
+                    "none" => NetworkMode::None,
+                    "bridge" => NetworkMode::Bridge,
+                    _ => NetworkMode::Named(value.to_owned()),
+                }
+            }
+            "env" => {
+                let (name, env_value) = value
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected `KEY=VALUE`, found `{}`", value))?;
+                self.env.push((name.to_owned(), env_value.to_owned()));
+            }
+            "init" => self.init_commands.push(value.to_owned()),
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+        Ok(())
+    }
+}
+
+/// Remove the double quotes around the value, if any.
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|it| it.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::dogana_images::ALPINE_IMAGE;
+
+    use super::*;
+
+    #[test]
+    fn header_sets_options() {
+        let lines = [
+            (1, "---"),
+            (2, "image = alpine"),
+            (3, "# a comment"),
+            (4, "timeout = \"30s\""),
+            (5, "env = GREETING=hello world"),
+            (6, "init = mkdir /data"),
+            (7, "---"),
+            (8, "$ true"),
+        ];
+        let (header, rest) = ConsoleTestHeader::parse(&lines).unwrap();
+        assert!(std::ptr::eq(header.image, &ALPINE_IMAGE));
+        assert_eq!(header.test_options.timeout, Some(Duration::from_secs(30)));
+        assert_eq!(
+            header.env,
+            [("GREETING".to_owned(), "hello world".to_owned())]
+        );
+        assert_eq!(header.init_commands, ["mkdir /data"]);
+        assert_eq!(rest, [(8, "$ true")]);
+    }
+
+    #[test]
+    fn file_without_header_uses_defaults() {
+        let lines = [(1, "$ true")];
+        let (header, rest) = ConsoleTestHeader::parse(&lines).unwrap();
+        assert!(std::ptr::eq(header.image, &DEBIAN_IMAGE));
+        assert_eq!(rest, lines);
+    }
+
+    #[test]
+    fn invalid_header_reports_line() {
+        let lines = [(1, "---"), (2, "image = windows"), (3, "---")];
+        let (line, reason) = ConsoleTestHeader::parse(&lines).unwrap_err();
+        assert_eq!(line, 2);
+        assert_eq!(reason, "unknown image `windows`");
+        assert_eq!(
+            ConsoleTestHeader::parse(&[(1, "---")]).unwrap_err().1,
+            "the header is not closed"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use super::NumberedLine;
+
+/// A fenced code block of a Markdown file.
+#[derive(Debug)]
+pub(crate) struct FencedBlock<'a> {
+    /// The info string of the block (e.g. `console` or `sh,ignore`).
+    pub info: &'a str,
+    /// The content of the block, with the line numbers.
+    pub content: Vec<NumberedLine<'a>>,
+}
+
+impl FencedBlock<'_> {
+    /// The language of the block, i.e. the first word of its info string.
+    pub fn language(&self) -> &str {
+        self.annotations().next().unwrap_or_default()
+    }
+
+    /// The words of the info string, which are separated by spaces or commas.
+    pub fn annotations(&self) -> impl Iterator<Item = &str> {
+        self.info
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|it| !it.is_empty())
+    }
+}
+
+/// Extract the fenced code blocks (delimited by backticks or tildes) from the numbered lines of a
+/// Markdown file. An unclosed block ends with the file.
+pub(crate) fn fenced_blocks<'a>(lines: &[NumberedLine<'a>]) -> Vec<FencedBlock<'a>> {
+    let mut blocks = vec![];
+    let mut current: Option<(String, FencedBlock)> = None;
+    for (number, line) in lines {
+        let trimmed = line.trim_start();
+        match current.take() {
+            Some((fence, block)) => {
+                // The closing fence is at least as long as the opening one.
+                if trimmed.trim_end().starts_with(&fence)
+                    && trimmed.trim_end().chars().all(|c| fence.starts_with(c))
+                {
+                    blocks.push(block);
+                } else {
+                    let mut block = block;
+                    block.content.push((*number, line));
+                    current = Some((fence, block));
+                }
+            }
+            None => {
+                if let Some(fence) = opening_fence(trimmed) {
+                    current = Some((
+                        fence.to_owned(),
+                        FencedBlock {
+                            info: trimmed[fence.len()..].trim(),
+                            content: vec![],
+                        },
+                    ));
+                }
+            }
+        }
+    }
+    blocks.extend(current.map(|(_, block)| block));
+    blocks
+}
+
+/// The opening fence of the line, if any.
+fn opening_fence(line: &str) -> Option<&str> {
+    let fence_char = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = line.chars().take_while(|c| *c == fence_char).count();
+    (length >= 3).then(|| &line[..length])
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    fn numbered_lines(content: &str) -> Vec<NumberedLine<'_>> {
+        content
+            .lines()
+            .enumerate()
+            .map(|(i, it)| (i + 1, it))
+            .collect()
+    }
+
+    #[test]
+    fn blocks_are_extracted_with_their_info() {
+        let content = indoc! {"
+            # Title
+            ```console
+            $ echo hi
+            hi
+            ```
+            text
+            ~~~~sh, ignore
+            ```
+            ~~~~
+        "};
+        let lines = numbered_lines(content);
+        let blocks = fenced_blocks(&lines);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].language(), "console");
+        assert_eq!(blocks[0].content, [(3, "$ echo hi"), (4, "hi")]);
+        assert_eq!(
+            blocks[1].annotations().collect::<Vec<_>>(),
+            ["sh", "ignore"]
+        );
+        assert_eq!(blocks[1].content, [(8, "```")]);
+    }
+}
@@ -1,5 +1,8 @@
-use hierrorchy::error_leaf;
+use hierrorchy::{error_leaf, error_node};
+use std::error::Error;
+use std::io::Error as IoError;
 use std::path::Path;
+use std::process::Command;
 use std::sync::{Arc, LazyLock};
 use which::which;
 
@@ -28,3 +31,137 @@ impl SupportedContainerManagerNotFound {
         SupportedContainerManagerNotFound {}
     }
 }
+
+/// Copy a path of a container to the host, returning whether the path exists in the container.
+/// Any other failure of the copy is an error.
+pub fn copy_from_container(
+    container_name: &str,
+    container_path: &str,
+    host_path: &Path,
+) -> Result<bool, IoError> {
+    let source = format!("{}:{}", container_name, container_path);
+    let result = Command::new(&*CONTAINER_MANAGER.clone())
+        .arg("cp")
+        .arg(&source)
+        .arg(host_path)
+        .output()?;
+    if result.status.success() {
+        return Ok(true);
+    }
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    if is_missing_path_error(&stderr) {
+        Ok(false)
+    } else {
+        Err(IoError::other(format!(
+            "failed to copy {}: {}",
+            source,
+            stderr.trim()
+        )))
+    }
+}
+
+/// Whether the error of a copy is caused by a missing container path, according to the messages
+/// of the supported container managers.
+fn is_missing_path_error(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    stderr.contains("could not find the file") || stderr.contains("could not be found on container")
+}
+
+/// Forcefully remove a container, stopping it if it is running.
+pub fn remove_container(container_name: &str) -> Result<(), ContainerManagerError> {
+    run_container_manager(&["rm", "-f", container_name])?;
+    Ok(())
+}
+
+/// Remove a container left by a previous run, if any, ignoring whether it exists.
+pub fn remove_stale_container(container_name: &str) -> Result<(), IoError> {
+    Command::new(&*CONTAINER_MANAGER.clone())
+        .args(["rm", "-f", container_name])
+        .output()?;
+    Ok(())
+}
+
+/// Send a signal (e.g. `KILL`) to the main process of a running container.
+pub fn kill_container(container_name: &str, signal: &str) -> Result<(), ContainerManagerError> {
+    run_container_manager(&["kill", "-s", signal, container_name])?;
+    Ok(())
+}
+
+/// Run a command in a running container.
+pub fn exec_in_container(
+    container_name: &str,
+    command: &[&str],
+) -> Result<(), ContainerManagerError> {
+    let mut args = vec!["exec", container_name];
+    args.extend_from_slice(command);
+    run_container_manager(&args)?;
+    Ok(())
+}
+
+/// Inspect a container, returning the given Go template evaluated on its state (e.g.
+/// `{{.State.OOMKilled}}`).
+pub fn inspect_container(
+    container_name: &str,
+    format: &str,
+) -> Result<String, ContainerManagerError> {
+    Ok(
+        run_container_manager(&["inspect", "--format", format, container_name])?
+            .trim()
+            .to_owned(),
+    )
+}
+
+/// Run the container manager with the given arguments, returning its stdout.
+fn run_container_manager(args: &[&str]) -> Result<String, ContainerManagerError> {
+    let result = Command::new(&*CONTAINER_MANAGER.clone())
+        .args(args)
+        .output()?;
+    if result.status.success() {
+        Ok(String::from_utf8_lossy(&result.stdout).into_owned())
+    } else {
+        Err(
+            ContainerCommandError::new(&args.join(" "), &String::from_utf8_lossy(&result.stderr))
+                .into(),
+        )
+    }
+}
+
+#[error_leaf(format!("container manager command `{}` failed: {}", self.command, self.stderr))]
+pub struct ContainerCommandError {
+    command: String,
+    stderr: String,
+}
+
+impl ContainerCommandError {
+    pub fn new(command: &str, stderr: &str) -> Self {
+        ContainerCommandError {
+            command: command.to_owned(),
+            stderr: stderr.to_owned(),
+        }
+    }
+}
+
+error_node! {
+    pub type ContainerManagerError<ContainerCommandError, IoError> = "failed to manage container"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_path_errors_are_recognized() {
+        assert!(is_missing_path_error(
+            "Error response from daemon: Could not find the file /out in container test"
+        ));
+        assert!(is_missing_path_error(
+            "Error: \"/out\" could not be found on container test: no such file or directory"
+        ));
+        assert!(!is_missing_path_error(
+            "Error: open /tmp/out: permission denied"
+        ));
+        assert!(!is_missing_path_error(
+            "Cannot connect to the Docker daemon at unix:///var/run/docker.sock"
+        ));
+    }
+}
@@ -1,6 +1,10 @@
 //! This module is the catalog of Dogana-built images.
 //! Each if the static [LazyLock]s below is an image variant. See below for the metadata key to use
 //! for configuring the images.
+//!
+//! When `create_test_user = true` is set in the metadata of a variant, its image contains an
+//! unprivileged user, named [TEST_USER] and with a home directory, which can run the tests through
+//! the `user` field of [DoganaTestOptions](crate::dogana_test::test_options::DoganaTestOptions).
 
 use std::sync::{Arc, LazyLock};
 
@@ -8,6 +12,14 @@ use crate::{
     image_builder::ImageBuilder, image_builder_factory::ImageBuilderFactory, image_name::ImageName,
 };
 
+pub use crate::metadata::dogana_metadata::ImageVariant;
+
+/// The name of the unprivileged test user created in the images.
+pub const TEST_USER: &str = "dogana";
+
+/// The UID (and GID) of the unprivileged test user created in the images.
+pub const TEST_USER_ID: u32 = 1000;
+
 /// The debian image, with metadata key `debian`.
 pub static DEBIAN_IMAGE: LazyLock<Arc<ImageName>> =
     LazyLock::new(|| match ImageBuilderFactory::debian_builder().build() {
@@ -21,3 +33,20 @@ pub static ALPINE_IMAGE: LazyLock<Arc<ImageName>> =
         Ok(image) => image.into(),
         Err(e) => panic!("failed to build image: {}", e),
     });
+
+/// The image variant with the given metadata key (e.g. `debian`), if it exists.
+pub fn image_by_key(key: &str) -> Option<&'static LazyLock<Arc<ImageName>>> {
+    match key {
+        "debian" => Some(&DEBIAN_IMAGE),
+        "alpine" => Some(&ALPINE_IMAGE),
+        _ => None,
+    }
+}
+
+/// The image of the given variant.
+pub fn variant_image(variant: ImageVariant) -> &'static LazyLock<Arc<ImageName>> {
+    match variant {
+        ImageVariant::Debian => &DEBIAN_IMAGE,
+        ImageVariant::Alpine => &ALPINE_IMAGE,
+    }
+}
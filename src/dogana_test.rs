@@ -1,26 +1,68 @@
 pub mod accept_exit_code;
 pub mod builder;
+mod diff;
+pub mod exit_status;
+mod fixture;
+pub mod fs_assertion;
+pub mod matrix;
+pub mod output_filter;
+pub mod output_matcher;
+mod process;
+pub mod resource_limits;
+pub mod session;
+pub mod signal;
+pub mod snapshot;
+pub mod test_definition;
 pub mod test_options;
+pub mod test_outcome;
+mod test_stdin;
+pub mod transcript;
 
 use std::{
     env::temp_dir,
     error::Error,
+    fmt::{self, Debug, Display, Formatter},
     fs,
     io::Error as IoError,
-    num::TryFromIntError,
     path::PathBuf,
-    process::{Command, Output},
+    process::{Command, Output, Stdio},
     str::Utf8Error,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use builder::DoganaTestBuilder;
-use hierrorchy::error_node;
+use exit_status::ExitStatus;
+use fixture::Fixture;
+use fs_assertion::FsAssertion;
+use hierrorchy::{error_leaf, error_node};
+use output_filter::{apply_filters, ContainerIdentity};
+use output_matcher::OutputMatcher;
+use process::{output_with_timeout, ProcessOutcome};
+use session::{Session, SessionError};
+use signal::{Signal, SignalDelivery, SignalScheduler};
+use snapshot::{Snapshot, SnapshotStream};
 use test_options::DoganaTestOptions;
+use test_outcome::TestOutcome;
+use test_stdin::TestStdin;
+use transcript::{check_transcript, split_commands, CommandOutcome, TranscriptStep};
 
-use crate::{container_manager::CONTAINER_MANAGER, image_name::ImageName, metadata::package_name};
+use crate::{
+    container_manager::{
+        copy_from_container, exec_in_container, inspect_container, kill_container,
+        remove_container, remove_stale_container, ContainerManagerError, CONTAINER_MANAGER,
+    },
+    image_name::ImageName,
+    metadata::package_name,
+};
 
 const INIT_PHASE_DELIMITER: &str = "===== INIT PHASE TERMINATED =====";
+const RUN_PHASE_DELIMITER: &str = "===== RUN PHASE TERMINATED =====";
+const FS_ASSERTION_DELIMITER: &str = "===== FS ASSERTION =====";
+const HOSTNAME_MAX_LENGTH: usize = 63;
+const TEST_SCRIPT_PATH: &str = "/test_script";
+const TEST_STDIN_PATH: &str = "/test_stdin";
+const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
 
 /// The type returned by test runs.
 pub type DoganaTestResult = Result<(), TestExecutionError>;
@@ -29,10 +71,21 @@ pub type DoganaTestResult = Result<(), TestExecutionError>;
 #[derive(Debug)]
 pub struct DoganaTest {
     test_name: String,
+    /// Whether the test name was set, instead of being random, thus it is stable across runs.
+    named: bool,
     base_image: Arc<ImageName>,
     init_commands: Vec<String>,
     run_commands: Vec<String>,
-    expected_output: String,
+    expected_output: Option<OutputMatcher>,
+    expected_stderr: Option<OutputMatcher>,
+    snapshots_dir: Option<PathBuf>,
+    stdin: Option<TestStdin>,
+    env: Vec<(String, String)>,
+    fixtures: Vec<Fixture>,
+    artifacts: Vec<String>,
+    fs_assertions: Vec<FsAssertion>,
+    signal: Option<SignalDelivery>,
+    transcript: Vec<TranscriptStep>,
     test_options: DoganaTestOptions,
 }
 
@@ -54,91 +107,768 @@ impl DoganaTest {
     ) -> DoganaTest {
         DoganaTest {
             test_name,
+            named: true,
             base_image: base_image.clone(),
             init_commands,
             run_commands,
-            expected_output,
+            expected_output: Some(OutputMatcher::Exact(expected_output)),
+            expected_stderr: None,
+            snapshots_dir: None,
+            stdin: None,
+            env: vec![],
+            fixtures: vec![],
+            artifacts: vec![],
+            fs_assertions: vec![],
+            signal: None,
+            transcript: vec![],
             test_options,
         }
     }
 
+    /// Run the test, returning an error if it cannot be executed or if its expectations are not
+    /// met.
     pub fn run(&self) -> DoganaTestResult {
-        let result = self.prepare_test_container()?.output()?;
-        let (output, err_output, exit_code) = self.extract_output(result)?;
-        self.test_options.accepted_exit_codes.accept(
-            exit_code,
-            &format!("stdout:\n{output}\n\nstderr:\n{err_output}"),
+        self.execute().map(|_| ())
+    }
+
+    /// Run the test and return its [TestOutcome].
+    ///
+    /// Unlike a failure in the container preparation, an unmet expectation (on the exit code or on
+    /// the output) is reported as an [AssertionFailure], which also carries the outcome of the
+    /// test.
+    pub fn execute(&self) -> Result<TestOutcome, TestExecutionError> {
+        let mut container = self.prepare_test_container(false)?;
+        let start = Instant::now();
+        let (result, signal_sent) = self.run_container(&mut container)?;
+        let duration = start.elapsed();
+        // The container is removed even if it cannot be inspected.
+        let artifacts_dir = self.collect_artifacts();
+        let oom_killed = self.check_oom_killed(&result);
+        if self.removes_container_after_run() {
+            remove_container(&self.container_name())?;
+        }
+        let artifacts_dir = artifacts_dir?;
+        let oom_killed = oom_killed?;
+        let (output, err_output, exit_status) = self.extract_output(result)?;
+        let (output, fs_probes_output) = if self.fs_assertions.is_empty() {
+            (output.as_str(), None)
+        } else {
+            split_fs_probes(&output)
+        };
+        let mut outcome = self.build_outcome(
+            output,
+            &err_output,
+            exit_status,
+            duration,
+            command_line(&container),
         );
-        let run_output = output
-            .lines()
-            .skip_while(|it| it != &INIT_PHASE_DELIMITER)
+        outcome.artifacts_dir = artifacts_dir;
+        outcome.oom_killed = oom_killed;
+        if outcome.oom_killed
+            && !self
+                .test_options
+                .accepted_exit_codes
+                .accepts(outcome.exit_status)
+        {
+            return Err(AssertionFailure::new(
+                format!(
+                    "the container has been killed as it exceeded its memory limit of {} bytes",
+                    self.test_options
+                        .resource_limits
+                        .memory
+                        .expect("OOM kills are detected only with a memory limit")
+                ),
+                outcome,
+            )
+            .into());
+        }
+        if let Some(signal) = self.signal.as_ref().filter(|_| !signal_sent) {
+            return Err(AssertionFailure::new(
+                format!(
+                    "signal {} was not sent, as {:?} did not occur while the test was running",
+                    signal.signal, signal.trigger
+                ),
+                outcome,
+            )
+            .into());
+        }
+        // A failed command of a transcript is reported before the exit code of the script, as it
+        // points at the command which diverged.
+        if let Some(failure) = check_transcript(&self.transcript, &outcome.commands) {
+            return Err(AssertionFailure::new(failure, outcome).into());
+        }
+        if !self
+            .test_options
+            .accepted_exit_codes
+            .accepts(outcome.exit_status)
+        {
+            return Err(AssertionFailure::new(
+                format!(
+                    "{} is not accepted by {:?}",
+                    outcome.exit_status, self.test_options.accepted_exit_codes
+                ),
+                outcome,
+            )
+            .into());
+        }
+        if let Some(expected_output) = &self.expected_output {
+            if let Err(mismatch) = expected_output.check(&outcome.run_output) {
+                return Err(AssertionFailure::new(
+                    format!("run output does not match the expected one: {}", mismatch),
+                    outcome,
+                )
+                .into());
+            }
+        }
+        if let Some(expected_stderr) = &self.expected_stderr {
+            if let Err(mismatch) = expected_stderr.check(&outcome.run_stderr) {
+                return Err(AssertionFailure::new(
+                    format!("run stderr does not match the expected one: {}", mismatch),
+                    outcome,
+                )
+                .into());
+            }
+        }
+        if let Some(mismatch) = self.check_snapshots(&outcome)? {
+            return Err(AssertionFailure::new(mismatch, outcome).into());
+        }
+        if let Some(failure) = self.check_fs_assertions(fs_probes_output) {
+            return Err(AssertionFailure::new(failure, outcome).into());
+        }
+        Ok(outcome)
+    }
+
+    /// Start the test container and return a [Session] which drives its run phase interactively,
+    /// once the init phase is terminated. The init phase is limited by
+    /// [DoganaTestOptions::timeout], if it is set.
+    ///
+    /// The expectations on the output, the snapshots and the filesystem assertions are not
+    /// checked in a session. As the stdin of the container is a pipe, the TTY mode is ignored.
+    pub fn spawn_session(&self) -> Result<Session, SessionError> {
+        let child = self
+            .prepare_test_container(true)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        Session::start(
+            child,
+            Some(self.container_name()),
+            self.removes_container_after_run(),
+            self.test_options.timeout,
+        )
+    }
+
+    /// Run the test container, killing it if it does not terminate before the timeout and sending
+    /// the test signal, if any. It returns the output of the container and whether the signal has
+    /// been sent.
+    fn run_container(&self, container: &mut Command) -> Result<(Output, bool), TestExecutionError> {
+        if self.test_options.timeout.is_none() && self.signal.is_none() {
+            return Ok((container.output()?, false));
+        }
+        let container_name = self.container_name();
+        let mut scheduler = self.signal.as_ref().map(SignalScheduler::new);
+        let mut signal_result = Ok(());
+        let outcome = output_with_timeout(
+            container,
+            self.test_options.timeout,
+            || {
+                // If the container cannot be killed, the container manager process is killed
+                // anyway.
+                let _ = kill_container(&container_name, "KILL");
+            },
+            |stdout| {
+                if let (Some(scheduler), Some(signal)) = (scheduler.as_mut(), &self.signal) {
+                    if scheduler.poll(stdout) {
+                        signal_result =
+                            exec_in_container(&container_name, &["sh", "-c", &signal.command()]);
+                    }
+                }
+            },
+        )?;
+        let signal_sent = scheduler.is_some_and(|it| it.sent());
+        match outcome {
+            // If the process exits right after the trigger, the container is gone before the
+            // signal is sent, thus the exec error is reported as a signal which was not sent.
+            ProcessOutcome::Exited(output) => Ok((output, signal_sent && signal_result.is_ok())),
+            ProcessOutcome::TimedOut { stdout, stderr } => {
+                if self.removes_container_after_run() {
+                    remove_container(&container_name)?;
+                }
+                Err(TestTimedOut::new(
+                    self.test_options
+                        .timeout
+                        .expect("only tests with a timeout can time out"),
+                    String::from_utf8_lossy(&stdout).into_owned(),
+                    String::from_utf8_lossy(&stderr).into_owned(),
+                )
+                .into())
+            }
+        }
+    }
+
+    /// Check the filesystem assertions against the output of their probes, returning a
+    /// description of the failed assertions, if any.
+    fn check_fs_assertions(&self, fs_probes_output: Option<&str>) -> Option<String> {
+        if self.fs_assertions.is_empty() {
+            return None;
+        }
+        let Some(fs_probes_output) = fs_probes_output else {
+            return Some("the filesystem assertions were not checked, as the test script did not terminate normally".to_owned());
+        };
+        // In TTY mode, the probes output has CRLF line endings.
+        let fs_probes_output = fs_probes_output.replace("\r\n", "\n");
+        // The output starts with a delimiter, thus the first section is empty.
+        let sections = fs_probes_output
+            .split(FS_ASSERTION_DELIMITER)
             .skip(1)
-            .map(|it| it.to_owned())
-            .reduce(|acc, it| acc + "\n" + &it)
-            .unwrap_or_else(String::new);
-        assert_eq!(run_output, self.expected_output);
-        Ok(())
+            .map(|it| it.trim_start_matches('\n'))
+            .chain(std::iter::repeat(""));
+        let failures = self
+            .fs_assertions
+            .iter()
+            .zip(sections)
+            .filter_map(|(assertion, section)| assertion.check(section).err())
+            .collect::<Vec<_>>();
+        if failures.is_empty() {
+            None
+        } else {
+            Some(format!(
+                "filesystem assertions failed:\n{}",
+                failures.join("\n")
+            ))
+        }
     }
 
-    fn prepare_test_script(&self) -> Result<PathBuf, TestScriptPreparationError> {
-        let test_script_content = format!(
-            "{}\necho '{}'\n{}",
+    /// Build the outcome of the test, applying the output filters to the captured outputs.
+    fn build_outcome(
+        &self,
+        output: &str,
+        err_output: &str,
+        exit_status: ExitStatus,
+        duration: Duration,
+        command: String,
+    ) -> TestOutcome {
+        let container_name = self.container_name();
+        let hostname = self.hostname();
+        let container = ContainerIdentity {
+            name: &container_name,
+            hostname: &hostname,
+        };
+        let filter = |it: &str| apply_filters(&self.test_options.output_filters, it, &container);
+        let (init_output, run_output) = split_phases(output);
+        let (init_stderr, run_stderr) = split_phases(err_output);
+        // The commands are split before filtering, as the filters may alter their delimiters.
+        let commands = split_commands(&self.transcript, &run_output, &run_stderr)
+            .into_iter()
+            .map(|it| CommandOutcome {
+                stdout: filter(&it.stdout),
+                stderr: filter(&it.stderr),
+                ..it
+            })
+            .collect();
+        let run_output = filter(&run_output);
+        let run_stderr = filter(&run_stderr);
+        TestOutcome {
+            stdout: filter(output),
+            stderr: filter(err_output),
+            init_output: filter(&init_output),
+            run_output,
+            init_stderr: filter(&init_stderr),
+            run_stderr,
+            commands,
+            exit_status,
+            oom_killed: false,
+            duration,
+            container_name,
+            command,
+            artifacts_dir: None,
+        }
+    }
+
+    /// Whether the container must be kept after the run phase, to inspect it.
+    fn inspects_container_after_run(&self) -> bool {
+        !self.artifacts.is_empty() || self.test_options.resource_limits.memory.is_some()
+    }
+
+    /// Whether the container has been killed for exceeding its memory limit.
+    fn check_oom_killed(&self, result: &Output) -> Result<bool, ContainerManagerError> {
+        if self.test_options.resource_limits.memory.is_none()
+            || !ExitStatus::from(result.status).killed_by(Signal::Kill)
+        {
+            return Ok(false);
+        }
+        Ok(inspect_container(&self.container_name(), "{{.State.OOMKilled}}")? == "true")
+    }
+
+    /// Whether the container must be removed explicitly after the run phase, as it cannot be
+    /// removed automatically by the container manager.
+    fn removes_container_after_run(&self) -> bool {
+        !self.test_options.keep_old_containers && self.inspects_container_after_run()
+    }
+
+    /// Copy the requested artifacts from the container to a host directory, returning the
+    /// directory. The artifacts which do not exist in the container are skipped, while the other
+    /// copy failures are errors.
+    fn collect_artifacts(&self) -> Result<Option<PathBuf>, IoError> {
+        if self.artifacts.is_empty() {
+            return Ok(None);
+        }
+        let artifacts_dir = temp_dir().join(format!(
+            "test-artifacts_{}_{}",
+            package_name(),
+            &self.test_name
+        ));
+        if artifacts_dir.exists() {
+            fs::remove_dir_all(&artifacts_dir)?;
+        }
+        let container_name = self.container_name();
+        for artifact in &self.artifacts {
+            let host_path = artifacts_dir.join(artifact.trim_start_matches('/'));
+            if let Some(parent) = host_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            copy_from_container(&container_name, artifact, &host_path)?;
+        }
+        Ok(Some(artifacts_dir))
+    }
+
+    fn check_snapshots(&self, outcome: &TestOutcome) -> Result<Option<String>, IoError> {
+        let Some(snapshots_dir) = &self.snapshots_dir else {
+            return Ok(None);
+        };
+        let update = snapshot::update_requested();
+        let mismatches = [
+            (SnapshotStream::Stdout, &outcome.run_output),
+            (SnapshotStream::Stderr, &outcome.run_stderr),
+        ]
+        .into_iter()
+        .map(|(stream, actual)| {
+            Snapshot::new(snapshots_dir, &self.test_name, stream).check(actual, update)
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        if mismatches.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(mismatches.join("\n\n")))
+        }
+    }
+
+    /// The content of the test script. Interactive containers have no terminal, thus their
+    /// script ignores the TTY mode.
+    fn test_script_content(&self, interactive: bool) -> String {
+        // In TTY mode, stdout and stderr are the same stream, thus the delimiters are written once.
+        let terminal = self.test_options.tty.as_ref().filter(|_| !interactive);
+        let run_commands = if self.transcript.is_empty() {
+            self.run_commands.join("\n")
+        } else {
+            self.transcript
+                .iter()
+                .map(|it| it.script(terminal.is_none()))
+                .collect::<String>()
+        };
+        let run_phase = match self.stdin {
+            // The run commands are grouped so that only they read the test stdin.
+            Some(_) => format!("{{\n{}\n}} < {}", run_commands, TEST_STDIN_PATH),
+            None => run_commands,
+        };
+        // A failure in copying the fixtures aborts the test before the init phase.
+        let setup_commands = self
+            .fixtures
+            .iter()
+            .enumerate()
+            .flat_map(|(index, fixture)| {
+                fixture.setup_commands(index).into_iter().map(move |it| {
+                    format!(
+                        "{} || {{ echo {} >&2; exit 1; }}\n",
+                        it,
+                        shell_quote(&format!(
+                            "failed to set up fixture {}, check that the test user can write it",
+                            fixture.container_path
+                        ))
+                    )
+                })
+            })
+            .collect::<String>();
+        let (terminal_setup, stderr_delimiter) = match terminal {
+            Some(terminal) => (
+                format!(
+                    "stty cols {} rows {} 2>/dev/null\n",
+                    terminal.columns, terminal.rows
+                ),
+                String::new(),
+            ),
+            None => (
+                String::new(),
+                format!("echo '{}' >&2\n", INIT_PHASE_DELIMITER),
+            ),
+        };
+        format!(
+            "{}{}{}\necho '{}'\n{}{}{}",
+            terminal_setup,
+            setup_commands,
             &self.init_commands.join("\n"),
             INIT_PHASE_DELIMITER,
-            &self.run_commands.join("\n")
-        );
+            stderr_delimiter,
+            self.fs_probes_hook(),
+            run_phase
+        )
+    }
+
+    /// The shell function, run on exit, which prints the state of the filesystem required by the
+    /// filesystem assertions. The hook preserves the exit code of the run phase and is run even
+    /// if the run commands exit early.
+    fn fs_probes_hook(&self) -> String {
+        if self.fs_assertions.is_empty() {
+            return String::new();
+        }
+        let probes = self
+            .fs_assertions
+            .iter()
+            .map(|it| {
+                format!(
+                    "echo '{}'\n{}\n",
+                    FS_ASSERTION_DELIMITER,
+                    it.probe_command()
+                )
+            })
+            .collect::<String>();
+        format!(
+            "__dogana_fs_probes() {{\necho\necho '{}'\n{}}}\ntrap '__dogana_status=$?; __dogana_fs_probes; exit $__dogana_status' EXIT\n",
+            RUN_PHASE_DELIMITER, probes
+        )
+    }
+
+    fn prepare_test_script(
+        &self,
+        interactive: bool,
+    ) -> Result<PathBuf, TestScriptPreparationError> {
         let test_script_path = temp_dir().join(format!(
             "test-script_{}_{}",
             package_name(),
             &self.test_name
         ));
-        fs::write(&test_script_path, test_script_content)?;
+        fs::write(&test_script_path, self.test_script_content(interactive))?;
         Ok(test_script_path)
     }
 
-    fn prepare_test_container(&self) -> Result<Command, TestContainerPreparationError> {
-        let container_name = format!("{}_dogana-test_{}", package_name(), &self.test_name);
-        let test_script_path = self.prepare_test_script()?;
+    /// Prepare the host file which is provided as stdin to the run phase, if any.
+    fn prepare_test_stdin(&self) -> Result<Option<PathBuf>, IoError> {
+        match &self.stdin {
+            Some(TestStdin::Bytes(bytes)) => {
+                let test_stdin_path =
+                    temp_dir().join(format!("test-stdin_{}_{}", package_name(), &self.test_name));
+                fs::write(&test_stdin_path, bytes)?;
+                Ok(Some(test_stdin_path))
+            }
+            Some(TestStdin::File(path)) => Ok(Some(path.canonicalize()?)),
+            None => Ok(None),
+        }
+    }
+
+    fn container_name(&self) -> String {
+        format!("{}_dogana-test_{}", package_name(), &self.test_name)
+    }
+
+    /// The hostname of the test container, derived from the test name as the container name may
+    /// not be a valid hostname.
+    fn hostname(&self) -> String {
+        format!("dogana-{}", &self.test_name)
+            .chars()
+            .map(|it| if it.is_ascii_alphanumeric() { it } else { '-' })
+            .take(HOSTNAME_MAX_LENGTH)
+            .collect::<String>()
+            .trim_end_matches('-')
+            .to_owned()
+    }
+
+    /// Prepare the container manager command which runs the test. An interactive container keeps
+    /// its stdin open.
+    fn prepare_test_container(
+        &self,
+        interactive: bool,
+    ) -> Result<Command, TestContainerPreparationError> {
+        let container_name = self.container_name();
+        // Named tests have a stable container name, which may be used by a kept or leaked
+        // container of a previous run.
+        if self.named {
+            remove_stale_container(&container_name)?;
+        }
+        let test_script_path = self.prepare_test_script(interactive)?;
         let mut cmd = std::process::Command::new(&*CONTAINER_MANAGER.clone());
         cmd.arg("run");
-        if !self.test_options.keep_old_containers {
+        if !self.test_options.keep_old_containers && !self.inspects_container_after_run() {
             cmd.arg("--rm");
         }
         cmd.args([
             "-v",
             &format!(
-                "{}:/test_script",
+                "{}:{}",
                 &test_script_path
                     .to_str()
-                    .expect("test script path is correct")
+                    .expect("test script path is correct"),
+                TEST_SCRIPT_PATH
             ),
+        ]);
+        if let Some(test_stdin_path) = self.prepare_test_stdin()? {
+            cmd.args([
+                "-v",
+                &format!(
+                    "{}:{}:ro",
+                    &test_stdin_path
+                        .to_str()
+                        .expect("test stdin path is a valid UTF-8 string"),
+                    TEST_STDIN_PATH
+                ),
+            ]);
+        }
+        for (index, fixture) in self.fixtures.iter().enumerate() {
+            let host_path = fixture.prepare_host_path(&format!(
+                "test-fixture_{}_{}_{}",
+                package_name(),
+                &self.test_name,
+                index
+            ))?;
+            cmd.args([
+                "-v",
+                &format!(
+                    "{}:{}:ro",
+                    &host_path
+                        .to_str()
+                        .expect("fixture path is a valid UTF-8 string"),
+                    fixture.mount_path(index)
+                ),
+            ]);
+        }
+        cmd.args(self.env_options());
+        cmd.args(self.test_options.resource_limits.container_options());
+        cmd.arg(self.network_option());
+        if interactive {
+            // The container manager refuses to allocate a terminal when its stdin is not one.
+            cmd.arg("-i");
+        } else if self.test_options.tty.is_some() {
+            cmd.arg("-t");
+        }
+        if let Some(user) = &self.test_options.user {
+            cmd.args(["--user", user]);
+        }
+        // The container manager creates the working directory if it does not exist.
+        if let Some(working_dir) = &self.test_options.working_dir {
+            cmd.args(["-w", working_dir]);
+        }
+        cmd.args([
             "--name",
             &container_name,
+            "--hostname",
+            &self.hostname(),
             &self.base_image,
-            "/usr/bin/env",
-            &self.test_options.shell.to_string(),
-            "/test_script",
         ]);
+        cmd.args(self.entrypoint());
         Ok(cmd)
     }
 
+    /// The container manager option which connects the test container to its network.
+    fn network_option(&self) -> String {
+        format!("--network={}", self.test_options.network)
+    }
+
+    /// The container manager options which set the test environment variables.
+    fn env_options(&self) -> Vec<String> {
+        if self.test_options.empty_env {
+            // The variables are set by the entrypoint, as the container environment is cleared.
+            vec![]
+        } else {
+            self.environment()
+                .iter()
+                .flat_map(|(key, value)| ["-e".to_owned(), format!("{}={}", key, value)])
+                .collect()
+        }
+    }
+
+    /// The environment variables of the test, including the ones describing the terminal in TTY
+    /// mode, unless they are set explicitly.
+    fn environment(&self) -> Vec<(String, String)> {
+        let mut environment = self.env.clone();
+        if let Some(terminal) = &self.test_options.tty {
+            for (key, value) in terminal.env() {
+                if !environment.iter().any(|(k, _)| k == key) {
+                    environment.push((key.to_owned(), value));
+                }
+            }
+        }
+        environment
+    }
+
+    /// The command run in the container, which executes the test script.
+    fn entrypoint(&self) -> Vec<String> {
+        let mut entrypoint = vec!["/usr/bin/env".to_owned()];
+        if self.test_options.empty_env {
+            entrypoint.push("-i".to_owned());
+            let environment = self.environment();
+            if !environment.iter().any(|(key, _)| key == "PATH") {
+                entrypoint.push(format!("PATH={}", DEFAULT_PATH));
+            }
+            entrypoint.extend(
+                environment
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value)),
+            );
+        }
+        entrypoint.push(self.test_options.shell.to_string());
+        entrypoint.push(TEST_SCRIPT_PATH.to_owned());
+        entrypoint
+    }
+
     fn extract_output(
         &self,
         cmd_output: Output,
-    ) -> Result<(String, String, u8), OutputExtractionError> {
+    ) -> Result<(String, String, ExitStatus), OutputExtractionError> {
         let output = std::str::from_utf8(&cmd_output.stdout)?.to_owned();
         let err_output = std::str::from_utf8(&cmd_output.stderr)?.to_owned();
-        let exit_status = cmd_output
-            .status
-            .code()
-            .expect("The container should return an exit code")
-            .try_into()?;
-        Ok((output, err_output, exit_status))
+        Ok((output, err_output, cmd_output.status.into()))
+    }
+}
+
+/// Split the stdout of the test script into the output of the test and the output of the
+/// filesystem probes, if they have been run.
+fn split_fs_probes(output: &str) -> (&str, Option<&str>) {
+    // The hook prints a newline before the delimiter, in case the output does not end with one.
+    match output.rfind(&format!("\n{}", RUN_PHASE_DELIMITER)) {
+        // In TTY mode, the newline is preceded by a carriage return.
+        Some(index) => (
+            output[..index]
+                .strip_suffix('\r')
+                .unwrap_or(&output[..index]),
+            Some(&output[index + RUN_PHASE_DELIMITER.len() + 1..]),
+        ),
+        None => (output, None),
+    }
+}
+
+/// Format a command as a shell command line, quoting the arguments when needed.
+fn command_line(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|it| shell_quote(&it.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quote a shell word, if it contains characters which have a special meaning for the shell.
+fn shell_quote(word: &str) -> String {
+    if !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c))
+    {
+        word.to_owned()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// Split an output stream of the test script into the output of the init phase and the output of
+/// the run phase. The delimiter is written on both stdout and stderr.
+fn split_phases(output: &str) -> (String, String) {
+    let init_output = output
+        .lines()
+        .take_while(|it| it != &INIT_PHASE_DELIMITER)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let run_output = output
+        .lines()
+        .skip_while(|it| it != &INIT_PHASE_DELIMITER)
+        .skip(1)
+        .collect::<Vec<_>>()
+        .join("\n");
+    (init_output, run_output)
+}
+
+/// An unmet expectation of a test.
+pub struct AssertionFailure {
+    reason: String,
+    outcome: Box<TestOutcome>,
+}
+
+impl Display for AssertionFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "assertion failed: {}\n\nexit status: {}\n\nstdout:\n{}\n\nstderr:\n{}\n\nreproduce with:\n{}",
+            self.reason,
+            self.outcome.exit_status,
+            self.outcome.stdout,
+            self.outcome.stderr,
+            self.outcome.command
+        )
+    }
+}
+
+/// The failure is shown as its message, as the test harness reports failed tests through [Debug].
+impl Debug for AssertionFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Error for AssertionFailure {}
+
+impl AssertionFailure {
+    pub fn new(reason: String, outcome: TestOutcome) -> Self {
+        AssertionFailure {
+            reason,
+            outcome: Box::new(outcome),
+        }
+    }
+
+    /// The description of the unmet expectation.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// The outcome of the failed test.
+    pub fn outcome(&self) -> &TestOutcome {
+        &self.outcome
+    }
+}
+
+#[error_leaf(format!("test timed out after {:?}\n\nstdout:\n{}\n\nstderr:\n{}", self.timeout, self.stdout, self.stderr))]
+pub struct TestTimedOut {
+    timeout: Duration,
+    stdout: String,
+    stderr: String,
+}
+
+impl TestTimedOut {
+    pub fn new(timeout: Duration, stdout: String, stderr: String) -> Self {
+        TestTimedOut {
+            timeout,
+            stdout,
+            stderr,
+        }
+    }
+
+    /// The timeout which expired.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// The standard output produced before the timeout.
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+
+    /// The standard error produced before the timeout.
+    pub fn stderr(&self) -> &str {
+        &self.stderr
     }
 }
 
 error_node! {
-    pub type OutputExtractionError<Utf8Error, TryFromIntError> = "failed to extract output"
+    pub type OutputExtractionError<Utf8Error> = "failed to extract output"
 }
 
 error_node! {
@@ -146,9 +876,306 @@ error_node! {
 }
 
 error_node! {
-    pub type TestContainerPreparationError<TestScriptPreparationError> = "failed to prepare test container"
+    pub type TestContainerPreparationError<TestScriptPreparationError, IoError> = "failed to prepare test container"
 }
 
-error_node! {
-    pub type TestExecutionError<TestContainerPreparationError, OutputExtractionError, IoError> = "failed to execute test"
+/// The error of a test run.
+///
+/// Unlike the other errors, its [Debug] output is its message, as the test harness reports the
+/// tests returning a failed [DoganaTestResult] through [Debug].
+pub enum TestExecutionError {
+    Variant0(TestContainerPreparationError),
+    Variant1(OutputExtractionError),
+    Variant2(IoError),
+    Variant3(AssertionFailure),
+    Variant4(ContainerManagerError),
+    Variant5(TestTimedOut),
+}
+
+impl Display for TestExecutionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to execute test: {}",
+            self.source()
+                .expect("TestExecutionError always has a source")
+        )
+    }
+}
+
+impl Debug for TestExecutionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Error for TestExecutionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Variant0(e) => Some(e),
+            Self::Variant1(e) => Some(e),
+            Self::Variant2(e) => Some(e),
+            Self::Variant3(e) => Some(e),
+            Self::Variant4(e) => Some(e),
+            Self::Variant5(e) => Some(e),
+        }
+    }
+}
+
+impl From<TestContainerPreparationError> for TestExecutionError {
+    fn from(value: TestContainerPreparationError) -> Self {
+        Self::Variant0(value)
+    }
+}
+
+impl From<OutputExtractionError> for TestExecutionError {
+    fn from(value: OutputExtractionError) -> Self {
+        Self::Variant1(value)
+    }
+}
+
+impl From<IoError> for TestExecutionError {
+    fn from(value: IoError) -> Self {
+        Self::Variant2(value)
+    }
+}
+
+impl From<AssertionFailure> for TestExecutionError {
+    fn from(value: AssertionFailure) -> Self {
+        Self::Variant3(value)
+    }
+}
+
+impl From<ContainerManagerError> for TestExecutionError {
+    fn from(value: ContainerManagerError) -> Self {
+        Self::Variant4(value)
+    }
+}
+
+impl From<TestTimedOut> for TestExecutionError {
+    fn from(value: TestTimedOut) -> Self {
+        Self::Variant5(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_options::{TerminalSize, TERM};
+
+    use super::*;
+
+    #[test]
+    fn split_phases_separates_init_and_run_output() {
+        let output = format!("init 1\ninit 2\n{INIT_PHASE_DELIMITER}\nrun 1\nrun 2\n");
+        let (init_output, run_output) = split_phases(&output);
+        assert_eq!(init_output, "init 1\ninit 2");
+        assert_eq!(run_output, "run 1\nrun 2");
+    }
+
+    #[test]
+    fn command_line_quotes_special_arguments() {
+        let mut cmd = Command::new("podman");
+        cmd.args(["run", "--name", "test", "echo 'a b'"]);
+        assert_eq!(
+            command_line(&cmd),
+            "podman run --name test 'echo '\\''a b'\\'''"
+        );
+    }
+
+    fn sample_test() -> DoganaTest {
+        DoganaTest {
+            test_name: "test".to_owned(),
+            named: true,
+            base_image: Arc::new(ImageName("image".to_owned())),
+            init_commands: vec!["init".to_owned()],
+            run_commands: vec!["run 1".to_owned(), "run 2".to_owned()],
+            expected_output: None,
+            expected_stderr: None,
+            snapshots_dir: None,
+            stdin: None,
+            env: vec![("KEY".to_owned(), "value".to_owned())],
+            fixtures: vec![],
+            artifacts: vec![],
+            fs_assertions: vec![],
+            signal: None,
+            transcript: vec![],
+            test_options: DoganaTestOptions::default(),
+        }
+    }
+
+    #[test]
+    fn stdin_is_redirected_only_to_run_phase() {
+        let mut test = sample_test();
+        test.stdin = Some(TestStdin::Bytes(b"input".to_vec()));
+        assert!(test
+            .test_script_content(false)
+            .ends_with(&format!("{{\nrun 1\nrun 2\n}} < {}", TEST_STDIN_PATH)));
+    }
+
+    #[test]
+    fn script_without_stdin_has_no_redirection() {
+        let test = sample_test();
+        assert!(!test.test_script_content(false).contains(TEST_STDIN_PATH));
+    }
+
+    #[test]
+    fn env_is_forwarded_to_container_manager() {
+        let test = sample_test();
+        assert_eq!(test.env_options(), ["-e", "KEY=value"]);
+        assert_eq!(test.entrypoint(), ["/usr/bin/env", "sh", TEST_SCRIPT_PATH]);
+    }
+
+    #[test]
+    fn network_is_forwarded_to_container_manager() {
+        let mut test = sample_test();
+        assert_eq!(test.network_option(), "--network=none");
+        test.test_options.network = test_options::NetworkMode::Named("dogana-net".to_owned());
+        assert_eq!(test.network_option(), "--network=dogana-net");
+    }
+
+    #[test]
+    fn empty_env_sets_only_test_variables() {
+        let mut test = sample_test();
+        test.test_options.empty_env = true;
+        assert!(test.env_options().is_empty());
+        assert_eq!(
+            test.entrypoint(),
+            [
+                "/usr/bin/env",
+                "-i",
+                &format!("PATH={}", DEFAULT_PATH),
+                "KEY=value",
+                "sh",
+                TEST_SCRIPT_PATH
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_probes_are_split_from_output() {
+        let output = format!("out\n\n{RUN_PHASE_DELIMITER}\n{FS_ASSERTION_DELIMITER}\npresent\n");
+        let (test_output, fs_probes_output) = split_fs_probes(&output);
+        assert_eq!(test_output, "out\n");
+        assert_eq!(
+            fs_probes_output,
+            Some(format!("\n{FS_ASSERTION_DELIMITER}\npresent\n").as_str())
+        );
+    }
+
+    #[test]
+    fn fs_assertions_are_checked_in_order() {
+        let mut test = sample_test();
+        test.fs_assertions = vec![FsAssertion::exists("/a"), FsAssertion::absent("/b")];
+        let probes_output =
+            format!("\n{FS_ASSERTION_DELIMITER}\npresent\n{FS_ASSERTION_DELIMITER}\npresent\n");
+        let failure = test
+            .check_fs_assertions(Some(&probes_output))
+            .expect("/b exists");
+        assert!(failure.contains("/b"));
+        assert!(!failure.contains("/a"));
+        assert!(test.check_fs_assertions(None).is_some());
+    }
+
+    #[test]
+    fn fs_assertions_are_checked_in_tty_mode() {
+        let mut test = sample_test();
+        test.test_options.tty = Some(TerminalSize {
+            columns: 80,
+            rows: 24,
+        });
+        test.fs_assertions = vec![FsAssertion::exists("/a"), FsAssertion::mode("/b", 0o644)];
+        let output = format!(
+            "out\r\n\r\n{RUN_PHASE_DELIMITER}\r\n{FS_ASSERTION_DELIMITER}\r\npresent\r\n{FS_ASSERTION_DELIMITER}\r\npresent\r\n644\r\n"
+        );
+        let (test_output, fs_probes_output) = split_fs_probes(&output);
+        assert_eq!(test_output, "out\r\n");
+        assert_eq!(test.check_fs_assertions(fs_probes_output), None);
+        let outcome = test.build_outcome(
+            &format!("{INIT_PHASE_DELIMITER}\r\n{test_output}"),
+            "",
+            ExitStatus::Exited(0),
+            Duration::ZERO,
+            "podman run".to_owned(),
+        );
+        assert_eq!(outcome.run_output, "out");
+    }
+
+    #[test]
+    fn tty_mode_sets_terminal_environment() {
+        let mut test = sample_test();
+        test.test_options.tty = Some(TerminalSize {
+            columns: 120,
+            rows: 40,
+        });
+        assert_eq!(
+            test.env_options(),
+            [
+                "-e",
+                "KEY=value",
+                "-e",
+                "COLUMNS=120",
+                "-e",
+                "LINES=40",
+                "-e",
+                &format!("TERM={}", TERM)
+            ]
+        );
+        let script = test.test_script_content(false);
+        assert!(script.starts_with("stty cols 120 rows 40"));
+        assert_eq!(script.matches(INIT_PHASE_DELIMITER).count(), 1);
+        let interactive_script = test.test_script_content(true);
+        assert!(!interactive_script.contains("stty"));
+        assert_eq!(interactive_script.matches(INIT_PHASE_DELIMITER).count(), 2);
+    }
+
+    #[test]
+    fn split_phases_without_delimiter_has_empty_run_output() {
+        let (init_output, run_output) = split_phases("some output");
+        assert_eq!(init_output, "some output");
+        assert_eq!(run_output, "");
+    }
+
+    #[test]
+    fn failures_are_debugged_as_their_message() {
+        let outcome = sample_test().build_outcome(
+            "out",
+            "",
+            ExitStatus::Exited(0),
+            Duration::ZERO,
+            "podman run".to_owned(),
+        );
+        let error = TestExecutionError::from(AssertionFailure::new(
+            "output differs\n-b\n+x".to_owned(),
+            outcome,
+        ));
+        let debug = format!("{:?}", error);
+        assert_eq!(debug, error.to_string());
+        assert!(
+            debug.starts_with("failed to execute test: assertion failed: output differs\n-b\n+x\n")
+        );
+    }
+
+    #[test]
+    fn transcript_is_split_before_filtering() {
+        let mut test = sample_test();
+        let mut step = TranscriptStep::new("false", "<N>");
+        step.accepted_exit_codes = accept_exit_code::AcceptExitCode::Specific(1);
+        test.transcript = vec![step];
+        test.test_options.output_filters =
+            vec![output_filter::OutputFilter::replace(r"\d+", "<N>")];
+        let output = format!(
+            "{}\n{}\n===== COMMAND TERMINATED: 1 =====\n",
+            INIT_PHASE_DELIMITER, 42
+        );
+        let outcome = test.build_outcome(
+            &output,
+            "",
+            ExitStatus::Exited(0),
+            Duration::ZERO,
+            "podman run".to_owned(),
+        );
+        assert_eq!(outcome.commands[0].stdout, "<N>");
+        assert_eq!(outcome.commands[0].exit_status, ExitStatus::Exited(1));
+        assert_eq!(check_transcript(&test.transcript, &outcome.commands), None);
+    }
 }
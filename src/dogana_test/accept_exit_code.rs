@@ -1,5 +1,12 @@
-/// The policy for accepting an exit code of a test.
-#[derive(Debug, Clone, Copy)]
+use std::ops::RangeInclusive;
+
+use super::{exit_status::ExitStatus, signal::Signal};
+
+/// The policy for accepting the exit status of a test.
+///
+/// A termination by a signal is matched by the policies on exit codes with the exit code
+/// `128 + signal number`, see [ExitStatus] for more details.
+#[derive(Debug, Clone)]
 pub enum AcceptExitCode {
     /// Accept all exit codes, i.e. ignore all errors.
     All,
@@ -9,15 +16,28 @@ pub enum AcceptExitCode {
     Error,
     /// Accept only a specific exit code.
     Specific(u8),
+    /// Accept any of the given exit codes.
+    AnyOf(Vec<u8>),
+    /// Accept the exit codes in the given range.
+    Range(RangeInclusive<u8>),
+    /// Accept all exit codes but the given ones.
+    NotIn(Vec<u8>),
+    /// Accept only a termination by the given signal.
+    KilledBy(Signal),
 }
 
 impl AcceptExitCode {
-    pub(crate) fn accept(&self, exit_code: u8, deny_message: &str) {
+    pub(crate) fn accepts(&self, exit_status: ExitStatus) -> bool {
+        let exit_code = exit_status.code();
         match self {
-            AcceptExitCode::Success => assert_eq!(0u8, exit_code, "{}", deny_message),
-            AcceptExitCode::Specific(c) => assert_eq!(c.to_owned(), exit_code, "{}", deny_message),
-            AcceptExitCode::Error => assert_ne!(0u8, exit_code, "{}", deny_message),
-            _ => (),
+            AcceptExitCode::All => true,
+            AcceptExitCode::Success => exit_code == 0,
+            AcceptExitCode::Error => exit_code != 0,
+            AcceptExitCode::Specific(c) => *c == exit_code,
+            AcceptExitCode::AnyOf(codes) => codes.contains(&exit_code),
+            AcceptExitCode::Range(range) => range.contains(&exit_code),
+            AcceptExitCode::NotIn(codes) => !codes.contains(&exit_code),
+            AcceptExitCode::KilledBy(signal) => exit_status.killed_by(*signal),
         }
     }
 }
@@ -28,46 +48,72 @@ mod tests {
 
     #[test]
     fn success_accept_zero() {
-        AcceptExitCode::Success.accept(0, "this message should not be displayed");
+        assert!(AcceptExitCode::Success.accepts(ExitStatus::Exited(0)));
     }
 
     #[test]
-    #[should_panic]
     fn success_deny_non_zero() {
-        AcceptExitCode::Success.accept(1, "Success does not accept non-zero exit codes");
+        assert!(!AcceptExitCode::Success.accepts(ExitStatus::Exited(1)));
     }
 
     #[test]
     fn error_accept_non_zero() {
-        AcceptExitCode::Error.accept(1, "this message should not be displayed");
+        assert!(AcceptExitCode::Error.accepts(ExitStatus::Exited(1)));
+        assert!(AcceptExitCode::Error.accepts(ExitStatus::Killed(9)));
     }
 
     #[test]
-    #[should_panic]
     fn error_deny_zero() {
-        AcceptExitCode::Error.accept(0, "Error does not accept 0 exit code");
+        assert!(!AcceptExitCode::Error.accepts(ExitStatus::Exited(0)));
     }
 
     #[test]
     fn specific_accept_inner() {
         let ec = 8;
-        AcceptExitCode::Specific(ec).accept(ec, "this message should no be displayed");
+        assert!(AcceptExitCode::Specific(ec).accepts(ExitStatus::Exited(ec)));
     }
 
     #[test]
-    #[should_panic]
     fn specific_deny_non_inner() {
         let ec = 8;
-        AcceptExitCode::Specific(ec).accept(
-            ec + 1,
-            "Specific does not accept other exit codes other than the inner one",
-        );
+        assert!(!AcceptExitCode::Specific(ec).accepts(ExitStatus::Exited(ec + 1)));
     }
 
     #[test]
     fn all_accept_any() {
-        AcceptExitCode::All.accept(0, "All accept any exit code");
-        AcceptExitCode::All.accept(1, "All accept any exit code");
-        AcceptExitCode::All.accept(8, "All accept any exit code");
+        assert!(AcceptExitCode::All.accepts(ExitStatus::Exited(0)));
+        assert!(AcceptExitCode::All.accepts(ExitStatus::Exited(1)));
+        assert!(AcceptExitCode::All.accepts(ExitStatus::Killed(9)));
+    }
+
+    #[test]
+    fn sets_accept_listed_codes() {
+        let any_of = AcceptExitCode::AnyOf(vec![0, 2]);
+        assert!(any_of.accepts(ExitStatus::Exited(2)));
+        assert!(!any_of.accepts(ExitStatus::Exited(1)));
+        let not_in = AcceptExitCode::NotIn(vec![0, 2]);
+        assert!(!not_in.accepts(ExitStatus::Exited(2)));
+        assert!(not_in.accepts(ExitStatus::Exited(1)));
+    }
+
+    #[test]
+    fn range_is_inclusive() {
+        let range = AcceptExitCode::Range(1..=3);
+        assert!(range.accepts(ExitStatus::Exited(1)));
+        assert!(range.accepts(ExitStatus::Exited(3)));
+        assert!(!range.accepts(ExitStatus::Exited(4)));
+    }
+
+    #[test]
+    fn killed_by_accept_only_the_signal() {
+        let killed_by = AcceptExitCode::KilledBy(Signal::Int);
+        assert!(killed_by.accepts(ExitStatus::Killed(2)));
+        assert!(!killed_by.accepts(ExitStatus::Killed(15)));
+        assert!(!killed_by.accepts(ExitStatus::Exited(1)));
+    }
+
+    #[test]
+    fn signal_terminations_match_exit_codes() {
+        assert!(AcceptExitCode::Specific(130).accepts(ExitStatus::Killed(2)));
     }
 }
@@ -1,10 +1,28 @@
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use uuid::Uuid;
 
-use crate::image_name::ImageName;
+use crate::{
+    dogana_images::{variant_image, ImageVariant},
+    image_name::ImageName,
+};
 
-use super::{test_options::DoganaTestOptions, DoganaTest};
+use super::{
+    fixture::{Fixture, FixtureSource},
+    fs_assertion::FsAssertion,
+    matrix::DoganaTestMatrix,
+    output_matcher::OutputMatcher,
+    signal::{Signal, SignalDelivery, SignalTrigger},
+    snapshot::default_snapshots_dir,
+    test_options::DoganaTestOptions,
+    test_stdin::TestStdin,
+    transcript::TranscriptStep,
+    DoganaTest,
+};
 
 /// Convenience struct for creating [DoganaTest]s.
 ///
@@ -12,44 +30,113 @@ use super::{test_options::DoganaTestOptions, DoganaTest};
 /// after building the test.
 ///
 /// Building a test requires:
-/// - the image name
-/// - the run commands
-/// - the expected output
+/// - the image name, or the image variants for a [matrix](Self::build_matrix)
+/// - the run commands, or the transcript steps
+/// - the expected output, unless the test uses snapshots or a transcript
 ///
 /// The other fields are optional.
 #[derive(Debug)]
 pub struct DoganaTestBuilder {
-    test_name: String,
+    test_name: Option<String>,
     test_options: DoganaTestOptions,
+    working_dir: Option<String>,
+    timeout: Option<Duration>,
     base_image: Option<Arc<ImageName>>,
+    base_images: Vec<ImageVariant>,
+    parallel_variants: bool,
+    cross_variant_check: bool,
     init_commands: Vec<String>,
     run_commands: Option<Vec<String>>,
-    expected_output: Option<String>,
+    expected_output: Option<OutputMatcher>,
+    expected_stderr: Option<OutputMatcher>,
+    snapshots_dir: Option<PathBuf>,
+    stdin: Option<TestStdin>,
+    env: Vec<(String, String)>,
+    fixtures: Vec<Fixture>,
+    artifacts: Vec<String>,
+    fs_assertions: Vec<FsAssertion>,
+    signal: Option<SignalDelivery>,
+    transcript: Vec<TranscriptStep>,
 }
 
 impl DoganaTestBuilder {
-    /// Initialize a builder with default [DoganaTestOptions] and a randomly-generated test name.
+    /// Initialize a builder with default [DoganaTestOptions]. Unless a test name is set, each
+    /// built test has a randomly-generated name.
     pub fn new() -> Self {
         DoganaTestBuilder {
-            test_name: Uuid::new_v4().to_string(),
+            test_name: None,
             test_options: Default::default(),
+            working_dir: None,
+            timeout: None,
             base_image: None,
+            base_images: vec![],
+            parallel_variants: false,
+            cross_variant_check: false,
             init_commands: vec![],
             run_commands: None,
             expected_output: None,
+            expected_stderr: None,
+            snapshots_dir: None,
+            stdin: None,
+            env: vec![],
+            fixtures: vec![],
+            artifacts: vec![],
+            fs_assertions: vec![],
+            signal: None,
+            transcript: vec![],
         }
     }
 
+    /// Set the name of the test, which is used to name its container and its snapshots. The
+    /// characters which are not valid in container names are replaced.
+    pub fn set_test_name(&mut self, test_name: &str) -> &mut Self {
+        self.test_name = Some(container_safe_name(test_name));
+        self
+    }
+
     pub fn set_test_options(&mut self, test_options: DoganaTestOptions) -> &mut Self {
         self.test_options = test_options;
         self
     }
 
+    /// Set the directory where the test script starts, overriding
+    /// [DoganaTestOptions::working_dir] whenever the test options are set.
+    pub fn set_working_dir(&mut self, working_dir: &str) -> &mut Self {
+        self.working_dir = Some(working_dir.to_string());
+        self
+    }
+
+    /// Set the maximum duration of the test container, overriding [DoganaTestOptions::timeout]
+    /// whenever the test options are set.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     pub fn set_base_image(&mut self, base_image: &Arc<ImageName>) -> &mut Self {
         self.base_image = Some(base_image.clone());
         self
     }
 
+    /// Set the image variants of a test [matrix](Self::build_matrix).
+    pub fn set_base_images(&mut self, base_images: &[ImageVariant]) -> &mut Self {
+        self.base_images = base_images.to_vec();
+        self
+    }
+
+    /// Run the variants of a test matrix concurrently.
+    pub fn set_parallel_variants(&mut self, parallel_variants: bool) -> &mut Self {
+        self.parallel_variants = parallel_variants;
+        self
+    }
+
+    /// Check that all the variants of a test matrix which pass produce the same run output and
+    /// stderr.
+    pub fn set_cross_variant_check(&mut self, cross_variant_check: bool) -> &mut Self {
+        self.cross_variant_check = cross_variant_check;
+        self
+    }
+
     pub fn set_init_commands(&mut self, init_commands: &[&str]) -> &mut Self {
         self.init_commands = init_commands.iter().map(|it| it.to_string()).collect();
         self
@@ -60,51 +147,284 @@ impl DoganaTestBuilder {
         self
     }
 
-    pub fn set_expected_output(&mut self, expected_output: &str) -> &mut Self {
-        self.expected_output = Some(expected_output.to_string());
+    /// Provide the given data, which may be binary, as stdin to the run commands. The init
+    /// commands do not receive it.
+    pub fn set_stdin(&mut self, stdin: impl AsRef<[u8]>) -> &mut Self {
+        self.stdin = Some(TestStdin::Bytes(stdin.as_ref().to_vec()));
+        self
+    }
+
+    /// Provide the content of the given host file as stdin to the run commands. The init commands
+    /// do not receive it.
+    pub fn set_stdin_file(&mut self, stdin_file: &Path) -> &mut Self {
+        self.stdin = Some(TestStdin::File(stdin_file.to_path_buf()));
+        self
+    }
+
+    /// Set an environment variable for the test. Setting a variable twice overrides its value.
+    pub fn set_env(&mut self, key: &str, value: &str) -> &mut Self {
+        self.env.retain(|(k, _)| k != key);
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set the given environment variables for the test.
+    pub fn set_envs(&mut self, envs: &[(&str, &str)]) -> &mut Self {
+        for (key, value) in envs {
+            self.set_env(key, value);
+        }
+        self
+    }
+
+    /// Set the given environment variables for the test with their values in the current
+    /// process. The variables which are not set in the current process are ignored.
+    pub fn inherit_env(&mut self, keys: &[&str]) -> &mut Self {
+        for key in keys {
+            if let Ok(value) = std::env::var(key) {
+                self.set_env(key, &value);
+            }
+        }
+        self
+    }
+
+    /// Copy the given host file or directory to the given container path before running the test
+    /// script. The test can modify the copy without affecting the host.
+    ///
+    /// A relative container path is relative to the working directory of the test.
+    pub fn add_fixture(&mut self, host_path: &Path, container_path: &str) -> &mut Self {
+        self.fixtures.push(Fixture {
+            source: FixtureSource::Path(host_path.to_path_buf()),
+            container_path: container_path.to_string(),
+            mode: None,
+            read_only_mount: false,
+        });
+        self
+    }
+
+    /// Create a file with the given content and mode (e.g. `0o644`) at the given container path
+    /// before running the test script. A relative container path is relative to the working
+    /// directory of the test.
+    pub fn add_fixture_content(
+        &mut self,
+        container_path: &str,
+        content: impl AsRef<[u8]>,
+        mode: u32,
+    ) -> &mut Self {
+        self.fixtures.push(Fixture {
+            source: FixtureSource::Content(content.as_ref().to_vec()),
+            container_path: container_path.to_string(),
+            mode: Some(mode),
+            read_only_mount: false,
+        });
+        self
+    }
+
+    /// Mount the given host file or directory read-only at the given container path. Unlike
+    /// [Self::add_fixture], the fixture is not copied, which is faster for large fixtures.
+    pub fn mount_fixture(&mut self, host_path: &Path, container_path: &str) -> &mut Self {
+        self.fixtures.push(Fixture {
+            source: FixtureSource::Path(host_path.to_path_buf()),
+            container_path: container_path.to_string(),
+            mode: None,
+            read_only_mount: true,
+        });
+        self
+    }
+
+    /// Copy the given container file or directory to the host after the run phase. The collected
+    /// artifacts are available through the [TestOutcome](super::test_outcome::TestOutcome).
+    pub fn collect_artifact(&mut self, container_path: &str) -> &mut Self {
+        self.artifacts.push(container_path.to_string());
+        self
+    }
+
+    /// Add an assertion on the container filesystem, checked after the run phase. See
+    /// [FsAssertion] for the available assertions.
+    pub fn add_fs_assertion(&mut self, fs_assertion: FsAssertion) -> &mut Self {
+        self.fs_assertions.push(fs_assertion);
+        self
+    }
+
+    /// Send a signal to the tested processes when the trigger occurs during the run phase, e.g. to
+    /// test the graceful shutdown on Ctrl-C. The test fails if the trigger does not occur. The
+    /// cleanup can be checked with filesystem assertions, which run after the signalled
+    /// processes terminate. See [SignalDelivery] for more details.
+    pub fn send_signal(&mut self, signal: Signal, trigger: SignalTrigger) -> &mut Self {
+        self.signal = Some(SignalDelivery { signal, trigger });
+        self
+    }
+
+    /// Add a run command with its own expectations, switching the test to transcript mode. In
+    /// transcript mode, the run commands are the ones of the steps, and a failure points at the
+    /// first command which does not meet its expectations. See [TranscriptStep] for more details.
+    pub fn add_transcript_step(&mut self, step: TranscriptStep) -> &mut Self {
+        self.transcript.push(step);
+        self
+    }
+
+    /// Set the expected standard output of the run phase. A string slice is matched exactly, see
+    /// [OutputMatcher] for the other matching policies.
+    pub fn set_expected_output(&mut self, expected_output: impl Into<OutputMatcher>) -> &mut Self {
+        self.expected_output = Some(expected_output.into());
+        self
+    }
+
+    /// Set the expected standard error of the run phase. If it is not set, the standard error is
+    /// not checked. A string slice is matched exactly, see [OutputMatcher] for the other matching
+    /// policies.
+    pub fn set_expected_stderr(&mut self, expected_stderr: impl Into<OutputMatcher>) -> &mut Self {
+        self.expected_stderr = Some(expected_stderr.into());
+        self
+    }
+
+    /// Expect the run phase not to write anything on the standard error.
+    pub fn expect_empty_stderr(&mut self) -> &mut Self {
+        self.set_expected_stderr("")
+    }
+
+    /// Compare the run phase stdout and stderr with the snapshots in `tests/snapshots`, see
+    /// [Self::set_snapshots_dir].
+    pub fn use_snapshots(&mut self) -> &mut Self {
+        self.set_snapshots_dir(&default_snapshots_dir())
+    }
+
+    /// Compare the run phase stdout and stderr with the snapshots in the given directory. The
+    /// snapshots are named after the test, thus a test name is required.
+    ///
+    /// When the environment variable
+    /// [UPDATE_SNAPSHOTS_ENV](super::snapshot::UPDATE_SNAPSHOTS_ENV) is set to `1`, the snapshots
+    /// are overwritten with the actual output; otherwise, on mismatch, the actual output is written
+    /// in a `.pending` file next to the snapshot for review.
+    pub fn set_snapshots_dir(&mut self, snapshots_dir: &Path) -> &mut Self {
+        self.snapshots_dir = Some(snapshots_dir.to_path_buf());
         self
     }
 
     pub fn build(&self) -> DoganaTest {
+        self.check_required_fields(false);
+        self.build_test(
+            self.base_image
+                .clone()
+                .expect("should have panicked if empty"),
+            self.test_name.clone(),
+        )
+    }
+
+    /// Build a test for each image variant, set with [Self::set_base_images], instead of the
+    /// image name. The name of each test, if set, is suffixed with its variant.
+    pub fn build_matrix(&self) -> DoganaTestMatrix {
+        self.check_required_fields(true);
+        let tests = self
+            .base_images
+            .iter()
+            .map(|variant| {
+                let test_name = self
+                    .test_name
+                    .as_ref()
+                    .map(|it| format!("{}-{}", it, variant));
+                let base_image = Arc::clone(variant_image(*variant));
+                (*variant, self.build_test(base_image, test_name))
+            })
+            .collect();
+        DoganaTestMatrix::new(tests, self.parallel_variants, self.cross_variant_check)
+    }
+
+    fn check_required_fields(&self, matrix: bool) {
         let mut uninitialized_required_values = vec![];
-        if self.base_image.is_none() {
+        if matrix && self.base_images.is_empty() {
+            uninitialized_required_values.push("base_images");
+        }
+        if !matrix && self.base_image.is_none() {
             uninitialized_required_values.push("base_image");
         }
-        if self.run_commands.is_none() {
+        if self.run_commands.is_some() && !self.transcript.is_empty() {
+            panic!("failed to initialize test: run_commands and transcript steps are exclusive");
+        }
+        if self.run_commands.is_none() && self.transcript.is_empty() {
             uninitialized_required_values.push("run_commands");
         }
-        if self.expected_output.is_none() {
+        if self.expected_output.is_none()
+            && self.snapshots_dir.is_none()
+            && self.transcript.is_empty()
+        {
             uninitialized_required_values.push("expected_output");
         }
+        if self.test_name.is_none() && self.snapshots_dir.is_some() {
+            uninitialized_required_values.push("test_name");
+        }
         if !uninitialized_required_values.is_empty() {
             panic!(
                 "failed to initialize test: the fields {} are not initialized",
                 uninitialized_required_values.join(", ")
             );
         }
+    }
+
+    fn build_test(&self, base_image: Arc<ImageName>, test_name: Option<String>) -> DoganaTest {
         DoganaTest {
-            test_name: self.test_name.clone(),
-            base_image: self
-                .base_image
-                .as_ref()
-                .expect("should have panicked if empty")
-                .clone(),
+            named: test_name.is_some(),
+            test_name: test_name.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            base_image,
             init_commands: self.init_commands.clone(),
-            run_commands: self
-                .run_commands
-                .as_ref()
-                .expect("should have panicked if empty")
-                .clone(),
-            expected_output: self
-                .expected_output
-                .as_ref()
-                .expect("should have panicked if empty")
-                .clone(),
-            test_options: self.test_options.clone(),
+            run_commands: match &self.run_commands {
+                Some(run_commands) => run_commands.clone(),
+                None => self
+                    .transcript
+                    .iter()
+                    .map(|it| it.command.clone())
+                    .collect(),
+            },
+            expected_output: self.expected_output.clone(),
+            expected_stderr: self.expected_stderr.clone(),
+            snapshots_dir: self.snapshots_dir.clone(),
+            stdin: self.stdin.clone(),
+            env: self.env.clone(),
+            fixtures: self.fixtures.clone(),
+            artifacts: self.artifacts.clone(),
+            fs_assertions: self.fs_assertions.clone(),
+            signal: self.signal.clone(),
+            transcript: self.transcript.clone(),
+            test_options: DoganaTestOptions {
+                working_dir: self
+                    .working_dir
+                    .clone()
+                    .or_else(|| self.test_options.working_dir.clone()),
+                timeout: self.timeout.or(self.test_options.timeout),
+                ..self.test_options.clone()
+            },
         }
     }
 }
 
+/// A name derived from the test name which is valid in container names. When characters are
+/// replaced, a hash of the original name is appended, so that different names (e.g. `a/b` and
+/// `a_b`) do not collide.
+fn container_safe_name(name: &str) -> String {
+    let safe_name = name
+        .chars()
+        .map(|it| {
+            if it.is_ascii_alphanumeric() || it == '-' || it == '.' || it == '_' {
+                it
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if safe_name == name {
+        safe_name
+    } else {
+        format!("{}-{:08x}", safe_name, fnv1a_hash(name))
+    }
+}
+
+/// The 32-bit FNV-1a hash of the text, which is stable across runs, unlike the hashers of the
+/// standard library.
+fn fnv1a_hash(text: &str) -> u32 {
+    text.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+    })
+}
+
 impl Default for DoganaTestBuilder {
     fn default() -> Self {
         Self::new()
@@ -134,4 +454,75 @@ mod tests {
             .build();
         assert!(t.type_id() == TypeId::of::<DoganaTest>());
     }
+
+    #[test]
+    fn working_dir_is_set_in_test_options() {
+        let t = DoganaTestBuilder::new()
+            .set_working_dir("/work")
+            .set_test_options(DoganaTestOptions::default())
+            .set_run_commands(&["true"])
+            .set_expected_output("")
+            .set_base_image(&Arc::new(ImageName("image".to_owned())))
+            .build();
+        assert_eq!(t.test_options.working_dir.as_deref(), Some("/work"));
+    }
+
+    #[test]
+    fn timeout_overrides_test_options() {
+        let t = DoganaTestBuilder::new()
+            .set_timeout(Duration::from_secs(30))
+            .set_test_options(DoganaTestOptions {
+                timeout: Some(Duration::from_secs(5)),
+                ..Default::default()
+            })
+            .set_run_commands(&["true"])
+            .set_expected_output("")
+            .set_base_image(&Arc::new(ImageName("image".to_owned())))
+            .build();
+        assert_eq!(t.test_options.timeout, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    #[should_panic(expected = "test_name")]
+    fn snapshots_without_test_name_panics() {
+        DoganaTestBuilder::new()
+            .set_run_commands(&["true"])
+            .use_snapshots()
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "exclusive")]
+    fn run_commands_and_transcript_are_exclusive() {
+        DoganaTestBuilder::new()
+            .set_run_commands(&["true"])
+            .add_transcript_step(TranscriptStep::new("true", ""))
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "the fields base_images are not initialized")]
+    fn matrix_requires_base_images() {
+        DoganaTestBuilder::new()
+            .set_base_image(&Arc::new(ImageName("image".to_owned())))
+            .set_run_commands(&["true"])
+            .set_expected_output("")
+            .build_matrix();
+    }
+
+    #[test]
+    fn test_name_is_made_container_safe() {
+        let safe_name = container_safe_name("cli/help output.console");
+        assert!(
+            safe_name.starts_with("cli_help_output.console-"),
+            "{}",
+            safe_name
+        );
+        assert_eq!(container_safe_name("a_b.console"), "a_b.console");
+        assert_ne!(container_safe_name("a/b.console"), "a_b.console");
+        assert_ne!(
+            container_safe_name("a/b.console"),
+            container_safe_name("a b.console")
+        );
+    }
 }
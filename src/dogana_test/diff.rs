@@ -0,0 +1,85 @@
+use std::io::IsTerminal;
+
+use similar::{ChangeTag, TextDiff};
+
+const CONTEXT_LINES: usize = 3;
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Whether the failure messages should be coloured, i.e. whether they are shown in a terminal and
+/// the user did not opt out through `NO_COLOR`.
+pub(crate) fn colours_enabled() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|it| it.is_empty())
+}
+
+/// Create a line-based unified diff between the expected and the actual output.
+pub(crate) fn unified_diff(expected: &str, actual: &str, coloured: bool) -> String {
+    // Outputs are captured without the trailing newline, which is added back to avoid the "no
+    // newline at end of file" markers.
+    let expected = format!("{}\n", expected);
+    let actual = format!("{}\n", actual);
+    let diff = TextDiff::from_lines(&expected, &actual);
+    let mut result =
+        paint("--- expected\n", CYAN, coloured) + &paint("+++ actual\n", CYAN, coloured);
+    for hunk in diff
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .iter_hunks()
+    {
+        result.push_str(&paint(&format!("{}\n", hunk.header()), CYAN, coloured));
+        for change in hunk.iter_changes() {
+            let line = format!("{}{}", change.tag(), change.value());
+            result.push_str(&match change.tag() {
+                ChangeTag::Delete => paint(&line, RED, coloured),
+                ChangeTag::Insert => paint(&line, GREEN, coloured),
+                ChangeTag::Equal => line,
+            });
+        }
+    }
+    result
+}
+
+fn paint(text: &str, colour: &str, coloured: bool) -> String {
+    if coloured {
+        // The colour is reset before the newline, so that it does not bleed into the next line.
+        match text.strip_suffix('\n') {
+            Some(t) => format!("{}{}{}\n", colour, t, RESET),
+            None => format!("{}{}{}", colour, text, RESET),
+        }
+    } else {
+        text.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn diff_shows_changed_lines_with_context() {
+        let diff = unified_diff("a\nb\nc", "a\nx\nc", false);
+        assert_eq!(
+            diff,
+            indoc! {"
+                --- expected
+                +++ actual
+                @@ -1,3 +1,3 @@
+                 a
+                -b
+                +x
+                 c
+            "}
+        );
+    }
+
+    #[test]
+    fn coloured_diff_paints_changes() {
+        let diff = unified_diff("a", "b", true);
+        assert!(diff.contains(&format!("{}-a{}", RED, RESET)));
+        assert!(diff.contains(&format!("{}+b{}", GREEN, RESET)));
+    }
+}
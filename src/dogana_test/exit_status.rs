@@ -0,0 +1,118 @@
+use std::{fmt::Display, os::unix::process::ExitStatusExt};
+
+use super::signal::Signal;
+
+/// The exit code which the shell and the container manager report for a process terminated by
+/// a signal, before adding the signal number.
+const SIGNAL_EXIT_CODE_BASE: u8 = 128;
+/// The highest signal number on Linux.
+const MAX_SIGNAL_NUMBER: u8 = 64;
+
+/// How the test container terminated.
+///
+/// A process terminated by a signal is reported by the shell and by the container manager with
+/// the exit code `128 + signal number`, thus such exit codes are considered signal terminations,
+/// even if the test script exited with them explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The test exited with the given exit code.
+    Exited(u8),
+    /// The test has been terminated by the signal with the given number.
+    Killed(u8),
+}
+
+impl ExitStatus {
+    /// The exit code of the test, where a termination by a signal is reported as
+    /// `128 + signal number`, modulo 256 as exit codes are bytes.
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Exited(code) => *code,
+            Self::Killed(signal) => SIGNAL_EXIT_CODE_BASE.wrapping_add(*signal),
+        }
+    }
+
+    /// Whether the test exited successfully.
+    pub fn success(&self) -> bool {
+        *self == Self::Exited(0)
+    }
+
+    /// Whether the test has been terminated by the signal.
+    pub fn killed_by(&self, signal: Signal) -> bool {
+        *self == Self::Killed(signal.number())
+    }
+}
+
+impl From<u8> for ExitStatus {
+    fn from(code: u8) -> Self {
+        match code.checked_sub(SIGNAL_EXIT_CODE_BASE) {
+            Some(signal @ 1..=MAX_SIGNAL_NUMBER) => Self::Killed(signal),
+            _ => Self::Exited(code),
+        }
+    }
+}
+
+impl From<std::process::ExitStatus> for ExitStatus {
+    fn from(status: std::process::ExitStatus) -> Self {
+        match (status.code(), status.signal()) {
+            // Exit codes are truncated to a byte by the operating system.
+            (Some(code), _) => Self::from(code as u8),
+            // The container manager itself has been terminated by a signal.
+            (None, Some(signal)) => Self::Killed(signal as u8),
+            (None, None) => unreachable!("a process either exits or is terminated by a signal"),
+        }
+    }
+}
+
+impl Display for ExitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exited(code) => write!(f, "exit code {}", code),
+            Self::Killed(signal) => match Signal::from_number(*signal) {
+                Some(name) => write!(f, "killed by signal {} (SIG{})", signal, name),
+                None => write!(f, "killed by signal {}", signal),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+
+    #[test]
+    fn signal_exit_codes_are_signal_terminations() {
+        assert_eq!(ExitStatus::from(0), ExitStatus::Exited(0));
+        assert_eq!(ExitStatus::from(128), ExitStatus::Exited(128));
+        assert_eq!(ExitStatus::from(130), ExitStatus::Killed(2));
+        assert_eq!(ExitStatus::from(255), ExitStatus::Exited(255));
+        assert_eq!(ExitStatus::Killed(9).code(), 137);
+    }
+
+    #[test]
+    fn out_of_range_signal_code_wraps() {
+        assert_eq!(ExitStatus::Killed(200).code(), 72);
+    }
+
+    #[test]
+    fn process_status_is_converted() {
+        let exited = Command::new("sh").args(["-c", "exit 3"]).status().unwrap();
+        assert_eq!(ExitStatus::from(exited), ExitStatus::Exited(3));
+        let killed = Command::new("sh")
+            .args(["-c", "kill -s TERM $$"])
+            .status()
+            .unwrap();
+        assert!(ExitStatus::from(killed).killed_by(Signal::Term));
+    }
+
+    #[test]
+    fn status_is_displayed_with_signal_name() {
+        assert_eq!(ExitStatus::Exited(1).to_string(), "exit code 1");
+        assert_eq!(
+            ExitStatus::Killed(9).to_string(),
+            "killed by signal 9 (SIGKILL)"
+        );
+        assert_eq!(ExitStatus::Killed(40).to_string(), "killed by signal 40");
+    }
+}
@@ -0,0 +1,145 @@
+use std::{fs, io::Error as IoError, path::PathBuf};
+
+use super::shell_quote;
+
+/// The directory of the container where copied fixtures are mounted before being copied to their
+/// destination.
+const FIXTURES_STAGING_DIR: &str = "/dogana_fixtures";
+
+/// The content of a fixture.
+#[derive(Debug, Clone)]
+pub(crate) enum FixtureSource {
+    /// A file or a directory of the host.
+    Path(PathBuf),
+    /// The content of a file, which is written in a temporary file before running the test.
+    Content(Vec<u8>),
+}
+
+/// A file or a directory made available in the test container before the test script runs.
+///
+/// Fixtures are either copied, so that the test can modify them without touching the host files,
+/// or mounted read-only.
+#[derive(Debug, Clone)]
+pub(crate) struct Fixture {
+    pub source: FixtureSource,
+    pub container_path: String,
+    pub mode: Option<u32>,
+    pub read_only_mount: bool,
+}
+
+impl Fixture {
+    /// Prepare the host path of the fixture, writing its content if needed. `temp_name` is used as
+    /// the name of the temporary file.
+    pub fn prepare_host_path(&self, temp_name: &str) -> Result<PathBuf, IoError> {
+        match &self.source {
+            FixtureSource::Path(path) => path.canonicalize().map_err(|e| {
+                IoError::new(
+                    e.kind(),
+                    format!("failed to find fixture {}: {}", path.display(), e),
+                )
+            }),
+            FixtureSource::Content(content) => {
+                let path = std::env::temp_dir().join(temp_name);
+                fs::write(&path, content)?;
+                Ok(path)
+            }
+        }
+    }
+
+    /// The container path where the fixture is mounted. `index` is the position of the fixture in
+    /// the test fixtures, which identifies the fixture in the staging directory.
+    pub fn mount_path(&self, index: usize) -> String {
+        if self.read_only_mount {
+            self.container_path.clone()
+        } else {
+            format!("{}/{}", FIXTURES_STAGING_DIR, index)
+        }
+    }
+
+    /// The shell commands which copy the fixture from the staging directory to its destination
+    /// and set its mode. `index` is the position of the fixture in the test fixtures.
+    ///
+    /// Read-only fixtures are mounted in place, thus they require no setup.
+    pub fn setup_commands(&self, index: usize) -> Vec<String> {
+        if self.read_only_mount {
+            return vec![];
+        }
+        let destination = shell_quote(&self.container_path);
+        let mut commands = vec![copy_command(
+            &shell_quote(&self.mount_path(index)),
+            &destination,
+        )];
+        if let Some(mode) = self.mode {
+            commands.push(format!("chmod {:o} {}", mode, destination));
+        }
+        commands
+    }
+}
+
+/// The shell command which copies a file or the content of a directory to the destination,
+/// creating its missing parent directories. The content of a directory is copied, so that it is
+/// not copied inside the destination if it already exists. The paths must be quoted.
+fn copy_command(source: &str, destination: &str) -> String {
+    format!(
+        "if [ -d {source} ]; then mkdir -p {destination} && cp -R {source}/. {destination}; else mkdir -p \"$(dirname {destination})\" && cp {source} {destination}; fi"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(read_only_mount: bool, mode: Option<u32>) -> Fixture {
+        Fixture {
+            source: FixtureSource::Content(b"content".to_vec()),
+            container_path: "/data/input file.txt".to_owned(),
+            mode,
+            read_only_mount,
+        }
+    }
+
+    #[test]
+    fn copied_fixture_is_staged_and_copied() {
+        let f = fixture(false, Some(0o640));
+        assert_eq!(f.mount_path(2), "/dogana_fixtures/2");
+        assert_eq!(
+            f.setup_commands(2),
+            [
+                "if [ -d /dogana_fixtures/2 ]; then mkdir -p '/data/input file.txt' && cp -R /dogana_fixtures/2/. '/data/input file.txt'; else mkdir -p \"$(dirname '/data/input file.txt')\" && cp /dogana_fixtures/2 '/data/input file.txt'; fi",
+                "chmod 640 '/data/input file.txt'"
+            ]
+        );
+    }
+
+    #[test]
+    fn read_only_fixture_is_mounted_in_place() {
+        let f = fixture(true, None);
+        assert_eq!(f.mount_path(0), "/data/input file.txt");
+        assert!(f.setup_commands(0).is_empty());
+    }
+
+    #[test]
+    fn directory_is_copied_into_existing_destination() {
+        let root = std::env::temp_dir().join(format!("dogana-fixture-{}", uuid::Uuid::new_v4()));
+        let source = root.join("source");
+        let destination = root.join("existing destination");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("nested/file"), "content").unwrap();
+        fs::create_dir_all(&destination).unwrap();
+        let command = copy_command(
+            &shell_quote(source.to_str().unwrap()),
+            &shell_quote(destination.to_str().unwrap()),
+        );
+        let status = std::process::Command::new("sh")
+            .args(["-c", &command])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(
+            fs::read_to_string(destination.join("nested/file")).unwrap(),
+            "content"
+        );
+        assert!(!destination.join("source").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+use regex::Regex;
+
+use super::shell_quote;
+
+const PRESENT: &str = "present";
+const ABSENT: &str = "absent";
+
+/// An assertion on the filesystem of the test container, checked after the run phase.
+///
+/// The assertions are checked inside the test container, even when the run commands exit
+/// early.
+#[derive(Debug, Clone)]
+pub enum FsAssertion {
+    /// The path must exist.
+    Exists(String),
+    /// The path must not exist.
+    Absent(String),
+    /// The file must have exactly the given content.
+    ContentEquals(String, Vec<u8>),
+    /// The content of the file must match the given regex.
+    ContentMatches(String, Regex),
+    /// The path must have the given permission bits (e.g. `0o644`).
+    Mode(String, u32),
+    /// The path must be owned by the given owner, in the form `user:group` or `uid:gid`.
+    Owner(String, String),
+    /// The directory must contain exactly the given entries, as paths relative to the directory.
+    TreeEquals(String, Vec<String>),
+}
+
+impl FsAssertion {
+    /// Assert that the path exists.
+    pub fn exists(path: &str) -> Self {
+        Self::Exists(path.to_owned())
+    }
+
+    /// Assert that the path does not exist.
+    pub fn absent(path: &str) -> Self {
+        Self::Absent(path.to_owned())
+    }
+
+    /// Assert that the file has exactly the given content.
+    pub fn content_equals(path: &str, content: impl AsRef<[u8]>) -> Self {
+        Self::ContentEquals(path.to_owned(), content.as_ref().to_vec())
+    }
+
+    /// Assert that the content of the file matches the given regex.
+    ///
+    /// # Panics
+    /// This function panics if the pattern is not a valid regex.
+    pub fn content_matches(path: &str, pattern: &str) -> Self {
+        match Regex::new(pattern) {
+            Ok(r) => Self::ContentMatches(path.to_owned(), r),
+            Err(e) => panic!("failed to create filesystem assertion: {}", e),
+        }
+    }
+
+    /// Assert that the path has the given permission bits (e.g. `0o644`).
+    pub fn mode(path: &str, mode: u32) -> Self {
+        Self::Mode(path.to_owned(), mode)
+    }
+
+    /// Assert that the path is owned by the given owner, in the form `user:group` or `uid:gid`.
+    pub fn owner(path: &str, owner: &str) -> Self {
+        Self::Owner(path.to_owned(), owner.to_owned())
+    }
+
+    /// Assert that the directory contains exactly the given entries, as paths relative to the
+    /// directory (e.g. `["sub", "sub/file"]`).
+    pub fn tree_equals(path: &str, entries: &[&str]) -> Self {
+        Self::TreeEquals(
+            path.to_owned(),
+            entries.iter().map(|it| it.to_string()).collect(),
+        )
+    }
+
+    fn path(&self) -> &str {
+        match self {
+            Self::Exists(p)
+            | Self::Absent(p)
+            | Self::ContentEquals(p, _)
+            | Self::ContentMatches(p, _)
+            | Self::Mode(p, _)
+            | Self::Owner(p, _)
+            | Self::TreeEquals(p, _) => p,
+        }
+    }
+
+    /// The shell command which prints the state of the path required by the assertion. The first
+    /// line tells whether the path exists.
+    pub(crate) fn probe_command(&self) -> String {
+        let path = shell_quote(self.path());
+        let probe = match self {
+            Self::Exists(_) | Self::Absent(_) => None,
+            Self::ContentEquals(_, _) | Self::ContentMatches(_, _) => {
+                Some(format!("od -An -tx1 -v {}", path))
+            }
+            Self::Mode(_, _) => Some(format!("stat -c %a {}", path)),
+            Self::Owner(_, _) => Some(format!("stat -c '%U:%G %u:%g' {}", path)),
+            Self::TreeEquals(_, _) => Some(format!("(cd {} && find . -mindepth 1)", path)),
+        };
+        format!(
+            "if [ -e {path} ] || [ -L {path} ]; then echo {PRESENT}; {} 2>/dev/null; else echo {ABSENT}; fi",
+            probe.unwrap_or_else(|| "true".to_owned())
+        )
+    }
+
+    /// Check the output of the probe command, returning a description of the failure if the
+    /// assertion does not hold.
+    pub(crate) fn check(&self, probe_output: &str) -> Result<(), String> {
+        let mut lines = probe_output.lines();
+        let present = lines.next() == Some(PRESENT);
+        let state = lines.collect::<Vec<_>>();
+        let fail = |reason: String| Err(format!("{}: {}", self.path(), reason));
+        match self {
+            Self::Absent(_) if present => fail("expected to be absent, but it exists".to_owned()),
+            Self::Absent(_) => Ok(()),
+            _ if !present => fail("expected to exist, but it is absent".to_owned()),
+            Self::Exists(_) => Ok(()),
+            Self::ContentEquals(_, expected) => {
+                let actual = decode_hex(&state);
+                if &actual == expected {
+                    Ok(())
+                } else {
+                    fail(format!(
+                        "content differs\n\nexpected:\n{}\n\nactual:\n{}",
+                        String::from_utf8_lossy(expected),
+                        String::from_utf8_lossy(&actual)
+                    ))
+                }
+            }
+            Self::ContentMatches(_, regex) => {
+                let actual = decode_hex(&state);
+                if regex.is_match(&String::from_utf8_lossy(&actual)) {
+                    Ok(())
+                } else {
+                    fail(format!(
+                        "content does not match regex `{}`\n\nactual:\n{}",
+                        regex,
+                        String::from_utf8_lossy(&actual)
+                    ))
+                }
+            }
+            Self::Mode(_, expected) => {
+                let actual = state
+                    .first()
+                    .and_then(|it| u32::from_str_radix(it.trim(), 8).ok());
+                if actual == Some(*expected) {
+                    Ok(())
+                } else {
+                    fail(format!(
+                        "expected mode {:o}, found {}",
+                        expected,
+                        state.first().unwrap_or(&"none")
+                    ))
+                }
+            }
+            Self::Owner(_, expected) => {
+                let actual = state.first().unwrap_or(&"");
+                if actual.split(' ').any(|it| it == expected) {
+                    Ok(())
+                } else {
+                    fail(format!("expected owner {}, found {}", expected, actual))
+                }
+            }
+            Self::TreeEquals(_, expected) => {
+                let mut actual = state
+                    .iter()
+                    .map(|it| it.trim_start_matches("./").to_owned())
+                    .collect::<Vec<_>>();
+                actual.sort();
+                let mut expected = expected.clone();
+                expected.sort();
+                if actual == expected {
+                    Ok(())
+                } else {
+                    fail(format!(
+                        "directory tree differs\n\nexpected:\n{}\n\nactual:\n{}",
+                        expected.join("\n"),
+                        actual.join("\n")
+                    ))
+                }
+            }
+        }
+    }
+}
+
+fn decode_hex(lines: &[&str]) -> Vec<u8> {
+    lines
+        .iter()
+        .flat_map(|it| it.split_whitespace())
+        .filter_map(|it| u8::from_str_radix(it, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absent_path_fails_existence_assertions() {
+        assert!(FsAssertion::exists("/a").check(ABSENT).is_err());
+        assert!(FsAssertion::mode("/a", 0o644).check(ABSENT).is_err());
+        assert!(FsAssertion::absent("/a").check(ABSENT).is_ok());
+        assert!(FsAssertion::absent("/a").check(PRESENT).is_err());
+    }
+
+    #[test]
+    fn content_is_decoded_from_hex_dump() {
+        let probe_output = format!("{PRESENT}\n 68 65 6c 6c 6f 0a\n");
+        assert!(FsAssertion::content_equals("/a", "hello\n")
+            .check(&probe_output)
+            .is_ok());
+        assert!(FsAssertion::content_matches("/a", "^hel+o\n$")
+            .check(&probe_output)
+            .is_ok());
+        assert!(FsAssertion::content_equals("/a", "hello")
+            .check(&probe_output)
+            .is_err());
+    }
+
+    #[test]
+    fn mode_is_compared_as_octal() {
+        let probe_output = format!("{PRESENT}\n644");
+        assert!(FsAssertion::mode("/a", 0o644).check(&probe_output).is_ok());
+        let message = FsAssertion::mode("/a", 0o755)
+            .check(&probe_output)
+            .unwrap_err();
+        assert_eq!(message, "/a: expected mode 755, found 644");
+    }
+
+    #[test]
+    fn owner_accepts_names_and_ids() {
+        let probe_output = format!("{PRESENT}\ntester:users 1000:100");
+        assert!(FsAssertion::owner("/a", "tester:users")
+            .check(&probe_output)
+            .is_ok());
+        assert!(FsAssertion::owner("/a", "1000:100")
+            .check(&probe_output)
+            .is_ok());
+        assert!(FsAssertion::owner("/a", "root:root")
+            .check(&probe_output)
+            .is_err());
+    }
+
+    #[test]
+    fn tree_ignores_entries_order() {
+        let probe_output = format!("{PRESENT}\n./sub/file\n./sub\n./other");
+        assert!(
+            FsAssertion::tree_equals("/a", &["other", "sub", "sub/file"])
+                .check(&probe_output)
+                .is_ok()
+        );
+        assert!(FsAssertion::tree_equals("/a", &["other"])
+            .check(&probe_output)
+            .is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    thread,
+};
+
+use crate::dogana_images::ImageVariant;
+
+use super::{
+    diff::{colours_enabled, unified_diff},
+    test_outcome::TestOutcome,
+    DoganaTest, TestExecutionError,
+};
+
+pub type DoganaTestMatrixResult = Result<(), TestMatrixFailure>;
+
+/// The same test run on several image variants, created by
+/// [build_matrix](super::builder::DoganaTestBuilder::build_matrix).
+#[derive(Debug)]
+pub struct DoganaTestMatrix {
+    tests: Vec<(ImageVariant, DoganaTest)>,
+    parallel: bool,
+    cross_variant_check: bool,
+}
+
+impl DoganaTestMatrix {
+    pub(crate) fn new(
+        tests: Vec<(ImageVariant, DoganaTest)>,
+        parallel: bool,
+        cross_variant_check: bool,
+    ) -> Self {
+        DoganaTestMatrix {
+            tests,
+            parallel,
+            cross_variant_check,
+        }
+    }
+
+    /// The tests of the matrix, with their image variant.
+    pub fn tests(&self) -> &[(ImageVariant, DoganaTest)] {
+        &self.tests
+    }
+
+    /// Run the test on every variant. All the variants are run, even if one of them fails, and
+    /// the failures are reported together.
+    pub fn run(&self) -> DoganaTestMatrixResult {
+        let mut failures = vec![];
+        let mut outcomes = vec![];
+        for (variant, result) in self.execute() {
+            match result {
+                Ok(outcome) => outcomes.push((variant, outcome)),
+                Err(e) => failures.push((variant, e)),
+            }
+        }
+        let divergence = if self.cross_variant_check {
+            find_divergence(&outcomes)
+        } else {
+            None
+        };
+        if failures.is_empty() && divergence.is_none() {
+            Ok(())
+        } else {
+            Err(TestMatrixFailure::new(failures, divergence))
+        }
+    }
+
+    /// Run the test on every variant and return the result of each of them.
+    pub fn execute(&self) -> Vec<(ImageVariant, Result<TestOutcome, TestExecutionError>)> {
+        if !self.parallel {
+            return self
+                .tests
+                .iter()
+                .map(|(variant, test)| (*variant, test.execute()))
+                .collect();
+        }
+        thread::scope(|scope| {
+            let handles = self
+                .tests
+                .iter()
+                .map(|(variant, test)| (*variant, scope.spawn(|| test.execute())))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|(variant, handle)| {
+                    let result = handle
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e));
+                    (variant, result)
+                })
+                .collect()
+        })
+    }
+}
+
+/// Compare the run outputs of the variants with the ones of the first variant, returning a
+/// description of the first difference, if any.
+fn find_divergence(outcomes: &[(ImageVariant, TestOutcome)]) -> Option<String> {
+    let ((reference_variant, reference), others) = outcomes.split_first()?;
+    others.iter().find_map(|(variant, outcome)| {
+        let stream_divergence = |stream: &str, expected: &str, actual: &str| {
+            (expected != actual).then(|| {
+                format!(
+                    "the {} of {} differs from the one of {}:\n{}",
+                    stream,
+                    variant,
+                    reference_variant,
+                    unified_diff(expected, actual, colours_enabled())
+                )
+            })
+        };
+        stream_divergence("output", &reference.run_output, &outcome.run_output)
+            .or_else(|| stream_divergence("stderr", &reference.run_stderr, &outcome.run_stderr))
+    })
+}
+
+/// The failure of some variants of a test matrix, or the difference between their outputs.
+pub struct TestMatrixFailure {
+    failures: Vec<(ImageVariant, TestExecutionError)>,
+    divergence: Option<String>,
+}
+
+impl Display for TestMatrixFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "test matrix failed:")?;
+        for (variant, e) in &self.failures {
+            write!(f, "\n\n[{}] {}", variant, e)?;
+        }
+        if let Some(divergence) = &self.divergence {
+            write!(f, "\n\n{}", divergence)?;
+        }
+        Ok(())
+    }
+}
+
+/// The failure is shown as its message, as the test harness reports failed tests through [Debug].
+impl Debug for TestMatrixFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Error for TestMatrixFailure {}
+
+impl TestMatrixFailure {
+    pub fn new(
+        failures: Vec<(ImageVariant, TestExecutionError)>,
+        divergence: Option<String>,
+    ) -> Self {
+        TestMatrixFailure {
+            failures,
+            divergence,
+        }
+    }
+
+    /// The failed variants, with their error.
+    pub fn failures(&self) -> &[(ImageVariant, TestExecutionError)] {
+        &self.failures
+    }
+
+    /// The description of the difference between the outputs of the passed variants, if the
+    /// cross-variant check is enabled and they differ.
+    pub fn divergence(&self) -> Option<&str> {
+        self.divergence.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::dogana_test::exit_status::ExitStatus;
+
+    use super::*;
+
+    fn outcome(run_output: &str, run_stderr: &str) -> TestOutcome {
+        TestOutcome {
+            stdout: String::new(),
+            stderr: String::new(),
+            init_output: String::new(),
+            run_output: run_output.to_owned(),
+            init_stderr: String::new(),
+            run_stderr: run_stderr.to_owned(),
+            commands: vec![],
+            exit_status: ExitStatus::Exited(0),
+            oom_killed: false,
+            duration: Duration::ZERO,
+            container_name: "container".to_owned(),
+            command: "podman run".to_owned(),
+            artifacts_dir: None,
+        }
+    }
+
+    #[test]
+    fn identical_outputs_do_not_diverge() {
+        let outcomes = [
+            (ImageVariant::Debian, outcome("out", "err")),
+            (ImageVariant::Alpine, outcome("out", "err")),
+        ];
+        assert_eq!(find_divergence(&outcomes), None);
+        assert_eq!(find_divergence(&[]), None);
+    }
+
+    #[test]
+    fn divergence_is_labelled_by_variant() {
+        let outcomes = [
+            (ImageVariant::Debian, outcome("out", "err")),
+            (ImageVariant::Alpine, outcome("out", "musl err")),
+        ];
+        let divergence = find_divergence(&outcomes).unwrap();
+        assert!(
+            divergence.starts_with("the stderr of alpine differs from the one of debian:"),
+            "{}",
+            divergence
+        );
+    }
+
+    #[test]
+    fn failure_is_debugged_as_its_message() {
+        let failure = TestMatrixFailure::new(
+            vec![],
+            Some("the output of alpine differs from the one of debian:\n-a\n+b".to_owned()),
+        );
+        assert_eq!(
+            format!("{:?}", failure),
+            "test matrix failed:\n\nthe output of alpine differs from the one of debian:\n-a\n+b"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+static ANSI_ESCAPE_SEQUENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(\x07|\x1b\\)")
+        .expect("the ANSI escape sequence regex is valid")
+});
+
+/// A normalization applied to the captured outputs of a test, before they are compared with the
+/// expected ones and shown in failure messages.
+///
+/// Filters are applied in the order they are listed in the
+/// [DoganaTestOptions](super::test_options::DoganaTestOptions).
+#[derive(Debug, Clone)]
+pub enum OutputFilter {
+    /// Replace all the matches of the regex with the placeholder. The placeholder can reference
+    /// the regex capture groups (e.g. `$1`).
+    Replace { regex: Regex, placeholder: String },
+    /// Remove ANSI escape sequences, such as colour codes.
+    StripAnsiCodes,
+    /// Replace CRLF line endings with LF.
+    NormalizeLineEndings,
+    /// Remove the trailing whitespaces of each line and of the whole output.
+    TrimTrailingWhitespace,
+    /// Replace the name and the hostname of the test container with the placeholder.
+    ContainerName { placeholder: String },
+}
+
+impl OutputFilter {
+    /// Create a filter which replaces all the matches of the pattern with the placeholder.
+    ///
+    /// # Panics
+    /// This function panics if the pattern is not a valid regex.
+    pub fn replace(pattern: &str, placeholder: &str) -> Self {
+        match Regex::new(pattern) {
+            Ok(regex) => Self::Replace {
+                regex,
+                placeholder: placeholder.to_owned(),
+            },
+            Err(e) => panic!("failed to create output filter: {}", e),
+        }
+    }
+
+    /// Create a filter which replaces the name and the hostname of the test container with the
+    /// placeholder.
+    pub fn container_name(placeholder: &str) -> Self {
+        Self::ContainerName {
+            placeholder: placeholder.to_owned(),
+        }
+    }
+
+    fn apply(&self, output: &str, container: &ContainerIdentity) -> String {
+        match self {
+            Self::Replace { regex, placeholder } => {
+                regex.replace_all(output, placeholder.as_str()).into_owned()
+            }
+            Self::StripAnsiCodes => ANSI_ESCAPE_SEQUENCE.replace_all(output, "").into_owned(),
+            Self::NormalizeLineEndings => output.replace("\r\n", "\n"),
+            Self::TrimTrailingWhitespace => output
+                .split('\n')
+                .map(|it| it.trim_end())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim_end()
+                .to_owned(),
+            Self::ContainerName { placeholder } => output
+                .replace(container.name, placeholder)
+                .replace(container.hostname, placeholder),
+        }
+    }
+}
+
+/// The values identifying a test container, which can be redacted from the outputs.
+pub(crate) struct ContainerIdentity<'a> {
+    pub name: &'a str,
+    pub hostname: &'a str,
+}
+
+/// Apply all the filters, in order, to the output.
+pub(crate) fn apply_filters(
+    filters: &[OutputFilter],
+    output: &str,
+    container: &ContainerIdentity,
+) -> String {
+    filters.iter().fold(output.to_owned(), |acc, filter| {
+        filter.apply(&acc, container)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTAINER: ContainerIdentity = ContainerIdentity {
+        name: "pkg_dogana-test_name",
+        hostname: "dogana-name",
+    };
+
+    #[test]
+    fn replace_uses_placeholder() {
+        let filter = OutputFilter::replace(r"\d{4}-\d{2}-\d{2}", "[DATE]");
+        assert_eq!(
+            filter.apply("created on 2025-01-31", &CONTAINER),
+            "created on [DATE]"
+        );
+    }
+
+    #[test]
+    fn strip_ansi_codes_removes_colours() {
+        assert_eq!(
+            OutputFilter::StripAnsiCodes.apply("\x1b[1;31merror\x1b[0m: failed", &CONTAINER),
+            "error: failed"
+        );
+    }
+
+    #[test]
+    fn line_endings_and_trailing_whitespace_are_normalized() {
+        let filters = [
+            OutputFilter::NormalizeLineEndings,
+            OutputFilter::TrimTrailingWhitespace,
+        ];
+        assert_eq!(
+            apply_filters(&filters, "a  \r\nb\t\r\n\r\n", &CONTAINER),
+            "a\nb"
+        );
+    }
+
+    #[test]
+    fn container_name_replaces_name_and_hostname() {
+        assert_eq!(
+            OutputFilter::container_name("[CONTAINER]")
+                .apply("pkg_dogana-test_name running on dogana-name", &CONTAINER),
+            "[CONTAINER] running on [CONTAINER]"
+        );
+    }
+}
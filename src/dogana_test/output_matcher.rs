@@ -0,0 +1,240 @@
+use std::{fmt::Debug, sync::Arc};
+
+use regex::Regex;
+
+use super::diff::{colours_enabled, unified_diff};
+
+type Predicate = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// The policy for matching an output (stdout or stderr) of a test.
+///
+/// A matcher can be created from a string slice, in which case it matches the output exactly.
+#[derive(Clone)]
+pub enum OutputMatcher {
+    /// The output must be equal to the inner string.
+    Exact(String),
+    /// The output must match the inner regex. The regex is not anchored, thus use `^` and `$` to
+    /// match the whole output.
+    Regex(Regex),
+    /// The output must contain all the inner substrings.
+    ContainsAll(Vec<String>),
+    /// The output must contain exactly the inner lines, in any order.
+    UnorderedLines(Vec<String>),
+    /// The output must be equal to the inner pattern, where `[..]` matches any text within a line
+    /// and a line made only of `...` matches any number of lines.
+    Wildcard(String),
+    /// The output must satisfy the inner predicate. The string is the description of the predicate
+    /// used in failure messages.
+    Custom(String, Predicate),
+}
+
+impl OutputMatcher {
+    /// Create a matcher which accepts only the given output.
+    pub fn exact(expected: &str) -> Self {
+        Self::Exact(expected.to_owned())
+    }
+
+    /// Create a matcher which accepts the outputs matching the given regex.
+    ///
+    /// # Panics
+    /// This function panics if the pattern is not a valid regex.
+    pub fn regex(pattern: &str) -> Self {
+        match Regex::new(pattern) {
+            Ok(r) => Self::Regex(r),
+            Err(e) => panic!("failed to create output matcher: {}", e),
+        }
+    }
+
+    /// Create a matcher which accepts the outputs containing all the given substrings.
+    pub fn contains_all(substrings: &[&str]) -> Self {
+        Self::ContainsAll(substrings.iter().map(|it| it.to_string()).collect())
+    }
+
+    /// Create a matcher which accepts the outputs made of the given lines, in any order.
+    pub fn unordered_lines(lines: &[&str]) -> Self {
+        Self::UnorderedLines(lines.iter().map(|it| it.to_string()).collect())
+    }
+
+    /// Create a matcher which accepts the outputs equal to the given pattern, where `[..]` matches
+    /// any text within a line and a line made only of `...` matches any number of lines.
+    pub fn wildcard(pattern: &str) -> Self {
+        Self::Wildcard(pattern.to_owned())
+    }
+
+    /// Create a matcher which accepts the outputs satisfying the given predicate.
+    pub fn custom(
+        description: &str,
+        predicate: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self::Custom(description.to_owned(), Arc::new(predicate))
+    }
+
+    /// Check the given output, returning a description of the mismatch if the output is not
+    /// accepted.
+    pub fn check(&self, actual: &str) -> Result<(), String> {
+        match self {
+            Self::Exact(expected) => {
+                if actual == expected {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "output differs\n\n{}",
+                        unified_diff(expected, actual, colours_enabled())
+                    ))
+                }
+            }
+            Self::Regex(regex) => {
+                if regex.is_match(actual) {
+                    Ok(())
+                } else {
+                    Err(format!("output does not match regex `{}`", regex))
+                }
+            }
+            Self::ContainsAll(substrings) => {
+                let missing = substrings
+                    .iter()
+                    .filter(|it| !actual.contains(it.as_str()))
+                    .map(|it| format!("{:?}", it))
+                    .collect::<Vec<_>>();
+                if missing.is_empty() {
+                    Ok(())
+                } else {
+                    Err(format!("output does not contain {}", missing.join(", ")))
+                }
+            }
+            Self::UnorderedLines(lines) => {
+                let mut unexpected = vec![];
+                let mut missing = lines.iter().map(|it| it.as_str()).collect::<Vec<_>>();
+                for line in actual.lines() {
+                    match missing.iter().position(|it| *it == line) {
+                        Some(i) => {
+                            missing.swap_remove(i);
+                        }
+                        None => unexpected.push(line),
+                    }
+                }
+                if missing.is_empty() && unexpected.is_empty() {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "output lines do not match\n\nmissing lines:\n{}\n\nunexpected lines:\n{}",
+                        missing.join("\n"),
+                        unexpected.join("\n")
+                    ))
+                }
+            }
+            Self::Wildcard(pattern) => {
+                if wildcard_regex(pattern).is_match(&format!("{}\n", actual)) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "output differs\n\n{}",
+                        unified_diff(pattern, actual, colours_enabled())
+                    ))
+                }
+            }
+            Self::Custom(description, predicate) => {
+                if predicate(actual) {
+                    Ok(())
+                } else {
+                    Err(format!("output does not satisfy: {}", description))
+                }
+            }
+        }
+    }
+}
+
+/// Convert a wildcard pattern into a regex matching the whole output, followed by a newline.
+fn wildcard_regex(pattern: &str) -> Regex {
+    let lines = pattern
+        .split('\n')
+        .map(|line| {
+            if line == "..." {
+                r"(?:[^\n]*\n)*?".to_owned()
+            } else {
+                format!(r"{}\n", regex::escape(line).replace(r"\[\.\.\]", r"[^\n]*"))
+            }
+        })
+        .collect::<String>();
+    Regex::new(&format!(r"\A{}\z", lines)).expect("escaped patterns are valid regexes")
+}
+
+impl Debug for OutputMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact(expected) => f.debug_tuple("Exact").field(expected).finish(),
+            Self::Regex(regex) => f.debug_tuple("Regex").field(regex).finish(),
+            Self::ContainsAll(substrings) => {
+                f.debug_tuple("ContainsAll").field(substrings).finish()
+            }
+            Self::UnorderedLines(lines) => f.debug_tuple("UnorderedLines").field(lines).finish(),
+            Self::Wildcard(pattern) => f.debug_tuple("Wildcard").field(pattern).finish(),
+            Self::Custom(description, _) => f.debug_tuple("Custom").field(description).finish(),
+        }
+    }
+}
+
+impl From<&str> for OutputMatcher {
+    fn from(value: &str) -> Self {
+        Self::exact(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_accepts_only_equal_output() {
+        let matcher = OutputMatcher::exact("test");
+        assert!(matcher.check("test").is_ok());
+        assert!(matcher.check("test ").is_err());
+    }
+
+    #[test]
+    fn regex_accepts_matching_output() {
+        let matcher = OutputMatcher::regex(r"^pid: \d+$");
+        assert!(matcher.check("pid: 42").is_ok());
+        assert!(matcher.check("pid: abc").is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn regex_panics_on_invalid_pattern() {
+        OutputMatcher::regex("(");
+    }
+
+    #[test]
+    fn contains_all_reports_missing_substrings() {
+        let matcher = OutputMatcher::contains_all(&["foo", "bar"]);
+        assert!(matcher.check("foo and bar").is_ok());
+        let message = matcher.check("foo only").expect_err("bar is missing");
+        assert!(message.contains("\"bar\""));
+        assert!(!message.contains("\"foo\""));
+    }
+
+    #[test]
+    fn unordered_lines_ignores_line_order() {
+        let matcher = OutputMatcher::unordered_lines(&["a", "b", "b"]);
+        assert!(matcher.check("b\na\nb").is_ok());
+        assert!(matcher.check("a\nb").is_err());
+        assert!(matcher.check("a\nb\nb\nc").is_err());
+    }
+
+    #[test]
+    fn custom_uses_predicate() {
+        let matcher = OutputMatcher::custom("has 3 lines", |it| it.lines().count() == 3);
+        assert!(matcher.check("1\n2\n3").is_ok());
+        let message = matcher.check("1").expect_err("output has 1 line");
+        assert!(message.contains("has 3 lines"));
+    }
+
+    #[test]
+    fn wildcard_matches_inline_text_and_lines() {
+        let matcher = OutputMatcher::wildcard("version [..]\n...\ndone");
+        assert!(matcher.check("version 1.2.3\ndone").is_ok());
+        assert!(matcher.check("version 1.2.3\nstep 1\nstep 2\ndone").is_ok());
+        assert!(matcher.check("version 1.2.3\ndone\nextra").is_err());
+        assert!(OutputMatcher::wildcard("a.b").check("axb").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    io::{Error as IoError, Read},
+    process::{Child, Command, Output, Stdio},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The result of a process run with a timeout.
+pub(crate) enum ProcessOutcome {
+    /// The process exited before the timeout.
+    Exited(Output),
+    /// The process has been killed as it did not exit before the timeout. The captured outputs
+    /// are the ones produced until the process was killed.
+    TimedOut { stdout: Vec<u8>, stderr: Vec<u8> },
+}
+
+/// A buffer filled by a thread which reads a stream until it is closed.
+pub(crate) struct CapturedStream {
+    buffer: Arc<Mutex<Vec<u8>>>,
+    reader: JoinHandle<()>,
+}
+
+impl CapturedStream {
+    pub fn new(mut stream: impl Read + Send + 'static) -> Self {
+        let buffer = Arc::new(Mutex::new(vec![]));
+        let thread_buffer = buffer.clone();
+        let reader = thread::spawn(move || {
+            let mut chunk = [0u8; 4096];
+            while let Ok(n) = stream.read(&mut chunk) {
+                if n == 0 {
+                    break;
+                }
+                thread_buffer
+                    .lock()
+                    .expect("the reader thread does not panic while holding the lock")
+                    .extend_from_slice(&chunk[..n]);
+            }
+        });
+        CapturedStream { buffer, reader }
+    }
+
+    /// Call `f` on the content read so far.
+    pub fn inspect<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        f(&self
+            .buffer
+            .lock()
+            .expect("the reader thread does not panic while holding the lock"))
+    }
+
+    /// Wait until the stream is closed and return its whole content.
+    pub fn join(self) -> Vec<u8> {
+        let _ = self.reader.join();
+        self.buffer
+            .lock()
+            .expect("the reader thread does not panic while holding the lock")
+            .clone()
+    }
+}
+
+/// Run the command, killing it if it does not exit before the timeout, if any. On timeout,
+/// `on_timeout` is called before killing the process, to stop what the process is managing.
+///
+/// While the process runs, `on_stdout` is called periodically with the stdout read so far.
+pub(crate) fn output_with_timeout(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+    on_timeout: impl FnOnce(),
+    mut on_stdout: impl FnMut(&[u8]),
+) -> Result<ProcessOutcome, IoError> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let (stdout, stderr) = capture_outputs(&mut child);
+    let deadline = timeout.map(|it| Instant::now() + it);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(ProcessOutcome::Exited(Output {
+                status,
+                stdout: stdout.join(),
+                stderr: stderr.join(),
+            }));
+        }
+        stdout.inspect(&mut on_stdout);
+        if deadline.is_some_and(|it| Instant::now() >= it) {
+            on_timeout();
+            // The process may have exited in the meantime, thus a kill failure is not an error.
+            let _ = child.kill();
+            child.wait()?;
+            return Ok(ProcessOutcome::TimedOut {
+                stdout: stdout.join(),
+                stderr: stderr.join(),
+            });
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Capture the piped stdout and stderr of the child.
+pub(crate) fn capture_outputs(child: &mut Child) -> (CapturedStream, CapturedStream) {
+    (
+        CapturedStream::new(child.stdout.take().expect("stdout is piped")),
+        CapturedStream::new(child.stderr.take().expect("stderr is piped")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn process_exiting_before_timeout_returns_output() {
+        let outcome = output_with_timeout(
+            Command::new("sh").args(["-c", "echo out; echo err >&2; exit 2"]),
+            Some(Duration::from_secs(10)),
+            || panic!("the process should not time out"),
+            |_| {},
+        )
+        .unwrap();
+        match outcome {
+            ProcessOutcome::Exited(output) => {
+                assert_eq!(output.stdout, b"out\n");
+                assert_eq!(output.stderr, b"err\n");
+                assert_eq!(output.status.code(), Some(2));
+            }
+            ProcessOutcome::TimedOut { .. } => panic!("the process should not time out"),
+        }
+    }
+
+    #[test]
+    fn process_exceeding_timeout_is_killed_with_partial_output() {
+        let mut timed_out = false;
+        let outcome = output_with_timeout(
+            Command::new("sh").args(["-c", "echo partial; exec sleep 10"]),
+            Some(Duration::from_millis(500)),
+            || timed_out = true,
+            |_| {},
+        )
+        .unwrap();
+        assert!(timed_out);
+        match outcome {
+            ProcessOutcome::TimedOut { stdout, .. } => assert_eq!(stdout, b"partial\n"),
+            ProcessOutcome::Exited(_) => panic!("the process should time out"),
+        }
+    }
+
+    #[test]
+    fn stdout_is_watched_while_process_runs() {
+        let mut watched = vec![];
+        output_with_timeout(
+            Command::new("sh").args(["-c", "echo first; sleep 0.2; echo second"]),
+            None,
+            || panic!("the process should not time out"),
+            |stdout| watched = stdout.to_vec(),
+        )
+        .unwrap();
+        assert!(watched.starts_with(b"first\n"));
+    }
+}
@@ -0,0 +1,69 @@
+/// The resource limits of a test container.
+///
+/// All the limits are unset by default, thus the container can use all the resources allowed by
+/// the container manager.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceLimits {
+    /// The maximum memory, in bytes. Swap is disabled when the limit is set, so that exceeding
+    /// it kills the container.
+    pub memory: Option<u64>,
+    /// The maximum number of CPUs (e.g. `0.5` for half a CPU).
+    pub cpus: Option<f64>,
+    /// The maximum number of processes.
+    pub pids: Option<u32>,
+    /// The size, in bytes, of a tmpfs mounted on `/tmp`.
+    pub tmpfs_size: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// The container manager options which set the limits.
+    pub(crate) fn container_options(&self) -> Vec<String> {
+        let mut options = vec![];
+        if let Some(memory) = self.memory {
+            options.push(format!("--memory={}", memory));
+            options.push(format!("--memory-swap={}", memory));
+        }
+        if let Some(cpus) = self.cpus {
+            options.push(format!("--cpus={}", cpus));
+        }
+        if let Some(pids) = self.pids {
+            options.push(format!("--pids-limit={}", pids));
+        }
+        if let Some(tmpfs_size) = self.tmpfs_size {
+            options.push("--tmpfs".to_owned());
+            options.push(format!("/tmp:size={}", tmpfs_size));
+        }
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_limits_have_no_options() {
+        assert!(ResourceLimits::default().container_options().is_empty());
+    }
+
+    #[test]
+    fn limits_are_translated_to_options() {
+        let limits = ResourceLimits {
+            memory: Some(64 * 1024 * 1024),
+            cpus: Some(0.5),
+            pids: Some(32),
+            tmpfs_size: Some(1024),
+        };
+        assert_eq!(
+            limits.container_options(),
+            [
+                "--memory=67108864",
+                "--memory-swap=67108864",
+                "--cpus=0.5",
+                "--pids-limit=32",
+                "--tmpfs",
+                "/tmp:size=1024"
+            ]
+        );
+    }
+}
@@ -0,0 +1,445 @@
+use std::{
+    error::Error,
+    io::{Error as IoError, Read, Write},
+    process::{Child, ChildStdin},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use hierrorchy::{error_leaf, error_node};
+use regex::Regex;
+
+use super::{exit_status::ExitStatus, TestContainerPreparationError, INIT_PHASE_DELIMITER};
+use crate::container_manager::{kill_container, remove_container};
+
+/// The time each step of a session waits for the expected output, unless changed with
+/// [Session::set_step_timeout].
+pub const DEFAULT_STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An output stream of the session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionStream {
+    Stdout,
+    Stderr,
+}
+
+/// A chunk read by the reader threads. An empty chunk means that the stream has been closed.
+type Chunk = (SessionStream, Vec<u8>);
+
+/// The output of a stream which has not been consumed yet by the session steps.
+#[derive(Debug, Default)]
+struct PendingOutput {
+    buffer: Vec<u8>,
+    consumed: usize,
+    closed: bool,
+}
+
+impl PendingOutput {
+    /// The part of the pending output which is valid UTF-8, excluding a character which has not
+    /// been completely read yet.
+    fn text(&self) -> &str {
+        let pending = &self.buffer[self.consumed..];
+        match std::str::from_utf8(pending) {
+            Ok(text) => text,
+            Err(e) => {
+                std::str::from_utf8(&pending[..e.valid_up_to()]).expect("the prefix is valid UTF-8")
+            }
+        }
+    }
+
+    /// Discard the output consumed so far, so that it is not reported in failures.
+    fn discard_consumed(&mut self) {
+        self.buffer.drain(..self.consumed);
+        self.consumed = 0;
+    }
+}
+
+/// A test run interactively: the run phase is driven step by step, waiting for some output and
+/// writing to the stdin of the test.
+///
+/// Each step fails if its expected output does not appear before the step timeout. The container
+/// is stopped and removed when the session is dropped.
+///
+/// ```ignore
+/// let mut session = test.spawn_session()?;
+/// let exit_status = session
+///     .expect("Continue? [y/N]")?
+///     .send_line("y")?
+///     .expect_eof()?;
+/// assert!(exit_status.success());
+/// ```
+#[derive(Debug)]
+pub struct Session {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    chunks: Receiver<Chunk>,
+    stdout: PendingOutput,
+    stderr: PendingOutput,
+    step_timeout: Duration,
+    /// The name of the container, which is `None` when the process is not a container manager.
+    container_name: Option<String>,
+    removes_container: bool,
+}
+
+impl Session {
+    /// Create a session on the spawned container manager process, whose stdin, stdout and stderr
+    /// are piped, and wait for the end of the init phase, for at most `init_timeout` if it is set.
+    /// The container is stopped and removed through the container manager only if
+    /// `container_name` is set.
+    pub(crate) fn start(
+        mut child: Child,
+        container_name: Option<String>,
+        removes_container: bool,
+        init_timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        let (sender, chunks) = mpsc::channel();
+        read_stream(
+            child.stdout.take().expect("stdout is piped"),
+            SessionStream::Stdout,
+            sender.clone(),
+        );
+        read_stream(
+            child.stderr.take().expect("stderr is piped"),
+            SessionStream::Stderr,
+            sender,
+        );
+        let mut session = Session {
+            stdin: child.stdin.take(),
+            child,
+            chunks,
+            stdout: PendingOutput::default(),
+            stderr: PendingOutput::default(),
+            step_timeout: DEFAULT_STEP_TIMEOUT,
+            container_name,
+            removes_container,
+        };
+        let delimiter = format!("{}\n", INIT_PHASE_DELIMITER);
+        for stream in [SessionStream::Stdout, SessionStream::Stderr] {
+            session.wait_until(stream, "init phase", init_timeout, |text| {
+                text.find(&delimiter).map(|index| index + delimiter.len())
+            })?;
+            session.pending(stream).discard_consumed();
+        }
+        Ok(session)
+    }
+
+    /// Set the time each following step waits for the expected output.
+    pub fn set_step_timeout(&mut self, step_timeout: Duration) -> &mut Self {
+        self.step_timeout = step_timeout;
+        self
+    }
+
+    /// Wait until the text appears on stdout. The output up to the end of the text is consumed,
+    /// so that the next steps only look at the output that follows.
+    pub fn expect(&mut self, text: &str) -> Result<&mut Self, SessionError> {
+        self.wait_for(SessionStream::Stdout, &format!("expect {:?}", text), |it| {
+            it.find(text).map(|index| index + text.len())
+        })?;
+        Ok(self)
+    }
+
+    /// Wait until the text appears on stderr, consuming the stderr up to the end of the text.
+    pub fn expect_stderr(&mut self, text: &str) -> Result<&mut Self, SessionError> {
+        self.wait_for(
+            SessionStream::Stderr,
+            &format!("expect stderr {:?}", text),
+            |it| it.find(text).map(|index| index + text.len()),
+        )?;
+        Ok(self)
+    }
+
+    /// Wait until stdout matches the regex, consuming the output up to the end of the match.
+    ///
+    /// # Panics
+    /// This function panics if the pattern is not a valid regex.
+    pub fn expect_regex(&mut self, pattern: &str) -> Result<&mut Self, SessionError> {
+        let regex = match Regex::new(pattern) {
+            Ok(r) => r,
+            Err(e) => panic!("failed to create session step: {}", e),
+        };
+        self.wait_for(
+            SessionStream::Stdout,
+            &format!("expect regex `{}`", pattern),
+            |it| regex.find(it).map(|m| m.end()),
+        )?;
+        Ok(self)
+    }
+
+    /// Write the data to the stdin of the test.
+    pub fn send(&mut self, data: impl AsRef<[u8]>) -> Result<&mut Self, SessionError> {
+        let Some(stdin) = self.stdin.as_mut() else {
+            return Err(self.failure("send", "stdin has been closed"));
+        };
+        stdin.write_all(data.as_ref())?;
+        stdin.flush()?;
+        Ok(self)
+    }
+
+    /// Write the line, followed by a newline, to the stdin of the test.
+    pub fn send_line(&mut self, line: &str) -> Result<&mut Self, SessionError> {
+        self.send(format!("{}\n", line))
+    }
+
+    /// Close the stdin of the test, which then reads an end of file.
+    pub fn send_eof(&mut self) -> &mut Self {
+        self.stdin = None;
+        self
+    }
+
+    /// Wait until the test terminates, returning its exit status.
+    pub fn expect_eof(&mut self) -> Result<ExitStatus, SessionError> {
+        let deadline = Instant::now() + self.step_timeout;
+        while !(self.stdout.closed && self.stderr.closed) {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(self.failure("expect eof", "the test is still running"));
+            }
+            match self.chunks.recv_timeout(deadline - now) {
+                Ok(chunk) => self.receive(chunk),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        self.stdin = None;
+        Ok(self.child.wait()?.into())
+    }
+
+    /// Wait until `find` matches the pending output of the stream, returning the end of the
+    /// match, and consume the output up to it.
+    fn wait_for(
+        &mut self,
+        stream: SessionStream,
+        step: &str,
+        find: impl Fn(&str) -> Option<usize>,
+    ) -> Result<(), SessionError> {
+        self.wait_until(stream, step, Some(self.step_timeout), find)
+    }
+
+    /// Wait until `find` matches the pending output of the stream, for at most the timeout if it
+    /// is set, consuming the output up to the returned index.
+    fn wait_until(
+        &mut self,
+        stream: SessionStream,
+        step: &str,
+        timeout: Option<Duration>,
+        find: impl Fn(&str) -> Option<usize>,
+    ) -> Result<(), SessionError> {
+        let deadline = timeout.map(|it| (Instant::now() + it, it));
+        loop {
+            let pending = self.pending(stream);
+            if let Some(end) = find(pending.text()) {
+                pending.consumed += end;
+                return Ok(());
+            }
+            if pending.closed {
+                return Err(self.failure(step, "the stream has been closed"));
+            }
+            let received = match deadline {
+                Some((deadline, timeout)) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(self.failure(step, &format!("timed out after {:?}", timeout)));
+                    }
+                    self.chunks.recv_timeout(deadline - now)
+                }
+                None => self
+                    .chunks
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(chunk) => self.receive(chunk),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(self.failure(step, "the stream has been closed"))
+                }
+            }
+        }
+    }
+
+    fn receive(&mut self, (stream, data): Chunk) {
+        let pending = self.pending(stream);
+        if data.is_empty() {
+            pending.closed = true;
+        } else {
+            pending.buffer.extend_from_slice(&data);
+        }
+    }
+
+    fn pending(&mut self, stream: SessionStream) -> &mut PendingOutput {
+        match stream {
+            SessionStream::Stdout => &mut self.stdout,
+            SessionStream::Stderr => &mut self.stderr,
+        }
+    }
+
+    fn failure(&self, step: &str, reason: &str) -> SessionError {
+        SessionStepFailed::new(
+            step.to_owned(),
+            reason.to_owned(),
+            String::from_utf8_lossy(&self.stdout.buffer).into_owned(),
+            String::from_utf8_lossy(&self.stderr.buffer).into_owned(),
+        )
+        .into()
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.stdin = None;
+        if let Ok(None) = self.child.try_wait() {
+            // If the container cannot be killed, the container manager process is killed anyway.
+            if let Some(container_name) = &self.container_name {
+                let _ = kill_container(container_name, "KILL");
+            }
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+        match &self.container_name {
+            Some(container_name) if self.removes_container => {
+                let _ = remove_container(container_name);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Read the stream in a new thread, sending its chunks until it is closed.
+fn read_stream(mut stream: impl Read + Send + 'static, kind: SessionStream, sender: Sender<Chunk>) {
+    thread::spawn(move || {
+        let mut chunk = [0u8; 4096];
+        while let Ok(n) = stream.read(&mut chunk) {
+            if n == 0 || sender.send((kind, chunk[..n].to_vec())).is_err() {
+                break;
+            }
+        }
+        let _ = sender.send((kind, vec![]));
+    });
+}
+
+#[error_leaf(format!("session step {} failed: {}\n\nstdout:\n{}\n\nstderr:\n{}", self.step, self.reason, self.stdout, self.stderr))]
+pub struct SessionStepFailed {
+    step: String,
+    reason: String,
+    stdout: String,
+    stderr: String,
+}
+
+impl SessionStepFailed {
+    pub fn new(step: String, reason: String, stdout: String, stderr: String) -> Self {
+        SessionStepFailed {
+            step,
+            reason,
+            stdout,
+            stderr,
+        }
+    }
+
+    /// The description of the failed step.
+    pub fn step(&self) -> &str {
+        &self.step
+    }
+
+    /// Why the step failed.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// The standard output of the run phase, until the failure.
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+
+    /// The standard error of the run phase, until the failure.
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
+}
+
+error_node! {
+    pub type SessionError<TestContainerPreparationError, SessionStepFailed, IoError> = "interactive session failed"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Command, Stdio};
+
+    use super::*;
+
+    fn start_script(script: &str) -> Result<Session, SessionError> {
+        start_script_after("", script, None)
+    }
+
+    fn start_script_after(
+        init_script: &str,
+        script: &str,
+        init_timeout: Option<Duration>,
+    ) -> Result<Session, SessionError> {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                "{0} echo '{1}'; echo '{1}' >&2; {2}",
+                init_script, INIT_PHASE_DELIMITER, script
+            ))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        Session::start(child, None, false, init_timeout)
+    }
+
+    #[test]
+    fn session_answers_prompts() {
+        let mut session = start_script(
+            "printf 'Continue? [y/N] '; read answer; echo \"answer: $answer\"; exit 3",
+        )
+        .unwrap();
+        let exit_status = session
+            .expect("Continue? [y/N]")
+            .unwrap()
+            .send_line("y")
+            .unwrap()
+            .expect_regex("answer: [a-z]")
+            .unwrap()
+            .expect_eof()
+            .unwrap();
+        assert_eq!(exit_status, ExitStatus::Exited(3));
+    }
+
+    #[test]
+    fn consumed_output_is_not_matched_again() {
+        let mut session = start_script("echo one; echo error >&2").unwrap();
+        session
+            .expect("one")
+            .unwrap()
+            .expect_stderr("error")
+            .unwrap();
+        let error = session.expect("one").unwrap_err().to_string();
+        assert!(error.contains("the stream has been closed"), "{}", error);
+        assert!(!error.contains(INIT_PHASE_DELIMITER), "{}", error);
+    }
+
+    #[test]
+    fn step_times_out_without_expected_output() {
+        let mut session = start_script("echo waiting; read answer").unwrap();
+        let error = session
+            .set_step_timeout(Duration::from_millis(200))
+            .expect("never")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("timed out"), "{}", error);
+        assert!(error.contains("waiting"), "{}", error);
+    }
+
+    #[test]
+    fn init_phase_waits_for_the_init_timeout() {
+        assert!(start_script_after("sleep 0.3;", "true", None).is_ok());
+        let error = start_script_after("sleep 2;", "true", Some(Duration::from_millis(200)))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("init phase"), "{}", error);
+        assert!(error.contains("timed out after 200ms"), "{}", error);
+    }
+}
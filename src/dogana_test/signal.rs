@@ -0,0 +1,210 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use super::INIT_PHASE_DELIMITER;
+
+/// A signal which can be sent to the tested processes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Hup,
+    Int,
+    Quit,
+    Kill,
+    Usr1,
+    Usr2,
+    Term,
+}
+
+impl Signal {
+    const ALL: [Signal; 7] = [
+        Self::Hup,
+        Self::Int,
+        Self::Quit,
+        Self::Kill,
+        Self::Usr1,
+        Self::Usr2,
+        Self::Term,
+    ];
+
+    /// The signal with the given number on Linux, if it is supported.
+    pub fn from_number(number: u8) -> Option<Signal> {
+        Self::ALL.into_iter().find(|it| it.number() == number)
+    }
+
+    /// The number of the signal on Linux.
+    pub fn number(&self) -> u8 {
+        match self {
+            Self::Hup => 1,
+            Self::Int => 2,
+            Self::Quit => 3,
+            Self::Kill => 9,
+            Self::Usr1 => 10,
+            Self::Usr2 => 12,
+            Self::Term => 15,
+        }
+    }
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Hup => "HUP",
+                Self::Int => "INT",
+                Self::Quit => "QUIT",
+                Self::Kill => "KILL",
+                Self::Usr1 => "USR1",
+                Self::Usr2 => "USR2",
+                Self::Term => "TERM",
+            }
+        )
+    }
+}
+
+/// When a signal is sent during the run phase.
+#[derive(Debug, Clone)]
+pub enum SignalTrigger {
+    /// As soon as the run phase starts. The first run command may not have started yet, thus
+    /// prefer [SignalTrigger::OnOutput] for processes which must set up their signal handlers.
+    RunPhaseStart,
+    /// After the given duration since the start of the run phase.
+    AfterDelay(Duration),
+    /// As soon as the stdout of the run phase contains the given text.
+    OnOutput(String),
+}
+
+/// A signal sent to the tested processes during the run phase, e.g. to test the graceful
+/// shutdown on Ctrl-C.
+///
+/// The signal is sent to every process of the test container but the test script, as a terminal
+/// does with the foreground processes. Thus the test script goes on with the next run commands
+/// after the signalled process terminates, and its exit code is the one of the last run command.
+#[derive(Debug, Clone)]
+pub struct SignalDelivery {
+    pub signal: Signal,
+    pub trigger: SignalTrigger,
+}
+
+impl SignalDelivery {
+    /// The shell command which sends the signal from inside the test container. `kill -1` sends
+    /// the signal to all the processes, except the container init (i.e. the test script) and the
+    /// sender itself.
+    pub(crate) fn command(&self) -> String {
+        format!("kill -s {} -- -1", self.signal)
+    }
+}
+
+/// Decide when to send a signal, by watching the stdout of the test while it runs.
+#[derive(Debug)]
+pub(crate) struct SignalScheduler<'a> {
+    delivery: &'a SignalDelivery,
+    /// When the run phase started, and the position of its output in stdout.
+    run_phase: Option<(Instant, usize)>,
+    sent: bool,
+}
+
+impl<'a> SignalScheduler<'a> {
+    pub fn new(delivery: &'a SignalDelivery) -> Self {
+        SignalScheduler {
+            delivery,
+            run_phase: None,
+            sent: false,
+        }
+    }
+
+    /// Whether the signal must be sent now, given the stdout captured so far. It returns `true`
+    /// at most once.
+    pub fn poll(&mut self, stdout: &[u8]) -> bool {
+        if self.sent {
+            return false;
+        }
+        let (start, run_output_index) = match self.run_phase {
+            Some(run_phase) => run_phase,
+            None => {
+                let Some(index) = find(stdout, INIT_PHASE_DELIMITER.as_bytes()) else {
+                    return false;
+                };
+                let run_phase = (Instant::now(), index + INIT_PHASE_DELIMITER.len());
+                self.run_phase = Some(run_phase);
+                run_phase
+            }
+        };
+        self.sent = match &self.delivery.trigger {
+            SignalTrigger::RunPhaseStart => true,
+            SignalTrigger::AfterDelay(delay) => start.elapsed() >= *delay,
+            SignalTrigger::OnOutput(text) => {
+                find(&stdout[run_output_index..], text.as_bytes()).is_some()
+            }
+        };
+        self.sent
+    }
+
+    /// Whether the signal has been sent.
+    pub fn sent(&self) -> bool {
+        self.sent
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivery(trigger: SignalTrigger) -> SignalDelivery {
+        SignalDelivery {
+            signal: Signal::Int,
+            trigger,
+        }
+    }
+
+    #[test]
+    fn signal_waits_for_run_phase() {
+        let delivery = delivery(SignalTrigger::RunPhaseStart);
+        let mut scheduler = SignalScheduler::new(&delivery);
+        assert!(!scheduler.poll(b"init output\n"));
+        let stdout = format!("init output\n{}\n", INIT_PHASE_DELIMITER);
+        assert!(scheduler.poll(stdout.as_bytes()));
+        assert!(!scheduler.poll(stdout.as_bytes()));
+        assert!(scheduler.sent());
+    }
+
+    #[test]
+    fn output_trigger_ignores_init_output() {
+        let delivery = delivery(SignalTrigger::OnOutput("ready".to_owned()));
+        let mut scheduler = SignalScheduler::new(&delivery);
+        let stdout = format!("ready\n{}\n", INIT_PHASE_DELIMITER);
+        assert!(!scheduler.poll(stdout.as_bytes()));
+        assert!(scheduler.poll(format!("{}server ready\n", stdout).as_bytes()));
+    }
+
+    #[test]
+    fn delay_is_measured_from_run_phase_start() {
+        let delivery = delivery(SignalTrigger::AfterDelay(Duration::from_millis(50)));
+        let mut scheduler = SignalScheduler::new(&delivery);
+        assert!(!scheduler.poll(INIT_PHASE_DELIMITER.as_bytes()));
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(scheduler.poll(INIT_PHASE_DELIMITER.as_bytes()));
+    }
+
+    #[test]
+    fn signal_is_sent_to_all_processes_but_init() {
+        assert_eq!(
+            delivery(SignalTrigger::RunPhaseStart).command(),
+            "kill -s INT -- -1"
+        );
+        assert_eq!(Signal::Term.number(), 15);
+        assert_eq!(Signal::from_number(15), Some(Signal::Term));
+    }
+}
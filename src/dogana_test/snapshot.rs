@@ -0,0 +1,168 @@
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use super::output_matcher::OutputMatcher;
+
+/// The environment variable which, when set to `1`, makes snapshot tests rewrite their snapshots
+/// with the actual output instead of comparing them.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "DOGANA_UPDATE_SNAPSHOTS";
+
+const PENDING_EXTENSION: &str = "pending";
+
+/// The output stream saved in a snapshot.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum SnapshotStream {
+    Stdout,
+    Stderr,
+}
+
+impl SnapshotStream {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
+/// A golden file containing the expected output of a stream of a test.
+///
+/// The snapshot of a test is stored in `<snapshots dir>/<test name>.<stream>`, while the actual
+/// output of a mismatching run is stored in `<snapshots dir>/<test name>.<stream>.pending`.
+pub(crate) struct Snapshot {
+    path: PathBuf,
+    pending_path: PathBuf,
+}
+
+impl Snapshot {
+    pub fn new(snapshots_dir: &Path, test_name: &str, stream: SnapshotStream) -> Self {
+        let path = snapshots_dir.join(format!("{}.{}", test_name, stream.extension()));
+        let pending_path = snapshots_dir.join(format!(
+            "{}.{}.{}",
+            test_name,
+            stream.extension(),
+            PENDING_EXTENSION
+        ));
+        Snapshot { path, pending_path }
+    }
+
+    /// Compare the actual output with the snapshot, returning a description of the mismatch if
+    /// they differ. On mismatch, the actual output is written in the pending file.
+    ///
+    /// If `update` is true, the snapshot is overwritten with the actual output instead.
+    pub fn check(&self, actual: &str, update: bool) -> Result<Option<String>, IoError> {
+        if update {
+            write_content(&self.path, actual)?;
+            remove_if_exists(&self.pending_path)?;
+            return Ok(None);
+        }
+        let mismatch = match read_content(&self.path)? {
+            Some(expected) => OutputMatcher::Exact(expected).check(actual).err(),
+            None => Some("the snapshot does not exist".to_owned()),
+        };
+        match mismatch {
+            Some(m) => {
+                write_content(&self.pending_path, actual)?;
+                Ok(Some(format!(
+                    "snapshot {} does not match: {}\n\nthe actual output has been written to {}; run the test with {}=1 to accept it",
+                    self.path.display(),
+                    m,
+                    self.pending_path.display(),
+                    UPDATE_SNAPSHOTS_ENV,
+                )))
+            }
+            None => {
+                remove_if_exists(&self.pending_path)?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Whether the user requested to update the snapshots through [UPDATE_SNAPSHOTS_ENV].
+pub(crate) fn update_requested() -> bool {
+    std::env::var(UPDATE_SNAPSHOTS_ENV).is_ok_and(|it| it == "1")
+}
+
+/// The default directory of the snapshots, i.e. `tests/snapshots` in the package directory.
+pub(crate) fn default_snapshots_dir() -> PathBuf {
+    PathBuf::from(
+        std::env::var("CARGO_MANIFEST_DIR")
+            .expect("CARGO_MANIFEST_DIR should be populated by cargo"),
+    )
+    .join("tests")
+    .join("snapshots")
+}
+
+// Snapshots are written with a trailing newline, as the captured outputs do not have one.
+fn write_content(path: &Path, content: &str) -> Result<(), IoError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, format!("{}\n", content))
+}
+
+fn read_content(path: &Path) -> Result<Option<String>, IoError> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(
+            content
+                .strip_suffix('\n')
+                .map(|it| it.to_owned())
+                .unwrap_or(content),
+        )),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), IoError> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn temp_snapshot() -> Snapshot {
+        let dir = std::env::temp_dir().join(format!("dogana-snapshots-{}", Uuid::new_v4()));
+        Snapshot::new(&dir, "test", SnapshotStream::Stdout)
+    }
+
+    #[test]
+    fn missing_snapshot_is_a_mismatch_and_writes_pending() {
+        let snapshot = temp_snapshot();
+        assert!(snapshot.check("output", false).unwrap().is_some());
+        assert_eq!(
+            read_content(&snapshot.pending_path).unwrap().as_deref(),
+            Some("output")
+        );
+    }
+
+    #[test]
+    fn update_writes_snapshot_and_removes_pending() {
+        let snapshot = temp_snapshot();
+        snapshot.check("old output", false).unwrap();
+        assert!(snapshot.check("output", true).unwrap().is_none());
+        assert!(!snapshot.pending_path.exists());
+        assert!(snapshot.check("output", false).unwrap().is_none());
+    }
+
+    #[test]
+    fn different_output_is_a_mismatch() {
+        let snapshot = temp_snapshot();
+        snapshot.check("output", true).unwrap();
+        assert!(snapshot.check("other output", false).unwrap().is_some());
+        assert_eq!(
+            read_content(&snapshot.path).unwrap().as_deref(),
+            Some("output")
+        );
+    }
+}
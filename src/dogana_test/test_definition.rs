@@ -0,0 +1,47 @@
+use std::{
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use crate::{console_test::ConsoleTest, image_name::ImageName};
+
+use super::{builder::DoganaTestBuilder, DoganaTestResult};
+
+/// A value which defines a test, i.e. which can be turned into a [DoganaTestBuilder].
+///
+/// The functions annotated with [`#[dogana::test]`](crate::test) return a test definition.
+pub trait TestDefinition {
+    fn into_builder(self) -> DoganaTestBuilder;
+}
+
+impl TestDefinition for DoganaTestBuilder {
+    fn into_builder(self) -> DoganaTestBuilder {
+        self
+    }
+}
+
+impl TestDefinition for ConsoleTest {
+    fn into_builder(self) -> DoganaTestBuilder {
+        self.builder()
+    }
+}
+
+/// Run a test definition with the settings of a `#[dogana::test]` attribute. The image and the
+/// timeout, when set, override the ones of the definition.
+#[doc(hidden)]
+pub fn run_test_definition(
+    definition: impl TestDefinition,
+    test_name: &str,
+    image: Option<&'static LazyLock<Arc<ImageName>>>,
+    timeout: Option<Duration>,
+) -> DoganaTestResult {
+    let mut builder = definition.into_builder();
+    builder.set_test_name(test_name);
+    if let Some(image) = image {
+        builder.set_base_image(image);
+    }
+    if let Some(timeout) = timeout {
+        builder.set_timeout(timeout);
+    }
+    builder.build().run()
+}
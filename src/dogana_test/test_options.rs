@@ -1,6 +1,8 @@
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
-use super::accept_exit_code::AcceptExitCode;
+use super::{
+    accept_exit_code::AcceptExitCode, output_filter::OutputFilter, resource_limits::ResourceLimits,
+};
 
 /// The options for a Dogana Test.
 ///
@@ -8,17 +10,63 @@ use super::accept_exit_code::AcceptExitCode;
 /// - `shell` = `Shell::Sh`
 /// - `keep_old_containers` = `false`
 /// - `accepted_exit_codes` = `AcceptExitCode::Success`
+/// - `output_filters` = `vec![]`
+/// - `empty_env` = `false`
+/// - `timeout` = `None`
+/// - `resource_limits` = `ResourceLimits::default()`
+/// - `network` = `NetworkMode::None`
+/// - `user` = `None`
+/// - `working_dir` = `None`
+/// - `tty` = `None`
 #[derive(Debug, Clone)]
 pub struct DoganaTestOptions {
     /// The shell used to run the test script. It must be available in the container `PATH`, thus
     /// you may need to add it to the `required_packages` section in the image variant metadata.
     pub shell: Shell,
-    /// Whether to keep the containers.
+    /// Whether to keep the containers. A kept container is removed when the test runs again.
     /// **This option is most useful when debugging a test. It is not recommended to enable this
     /// option for normal usage.**
     pub keep_old_containers: bool,
     /// See [AcceptExitCode] for more details.
     pub accepted_exit_codes: AcceptExitCode,
+    /// The filters applied to the captured outputs before comparing them with the expected ones.
+    /// See [OutputFilter] for more details.
+    pub output_filters: Vec<OutputFilter>,
+    /// Whether to run the test script in an empty environment, which contains only the
+    /// environment variables set for the test and a default `PATH` (unless it is set for the
+    /// test). Variables set by the image (e.g. `HOME`) are not available.
+    pub empty_env: bool,
+    /// The maximum duration of the test container. When it expires, the container is killed
+    /// and the test fails with a [TestTimedOut](super::TestTimedOut) error. If it is `None`, the
+    /// test can run indefinitely.
+    pub timeout: Option<Duration>,
+    /// See [ResourceLimits] for more details.
+    pub resource_limits: ResourceLimits,
+    /// The network of the test container. See [NetworkMode] for more details.
+    pub network: NetworkMode,
+    /// The user running the test script, as a name or as `uid:gid`. If it is `None`, the default
+    /// user of the image (i.e. root) is used.
+    ///
+    /// An unprivileged user can be created in the images, see
+    /// [dogana_images](crate::dogana_images) for more details.
+    ///
+    /// The whole test script runs as this user, including the copy of the fixtures, thus their
+    /// destinations must be writable by the user (e.g. in its home directory), unless they are
+    /// mounted read-only. The [working_dir](Self::working_dir) is created by the container manager
+    /// as root, thus the user can write in it only if it already exists with the right owner.
+    pub user: Option<String>,
+    /// The absolute path of the directory where the test script starts, which is created if it
+    /// is missing. If it is `None`, the default working directory of the image is used.
+    pub working_dir: Option<String>,
+    /// Whether to run the test script in a pseudo-terminal of the given size, to test the
+    /// behaviour of CLIs when their output is a terminal (e.g. colours, progress bars, prompts).
+    ///
+    /// In TTY mode, stdout and stderr are combined in a single terminal stream, which is captured
+    /// as stdout; thus the stderr of the test is empty. The terminal uses CRLF line endings: they
+    /// are removed from the init and run outputs, which are split into lines, but they are kept
+    /// in the whole stdout, where they can be normalized with
+    /// [OutputFilter::NormalizeLineEndings].
+    pub tty: Option<TerminalSize>,
 }
 
 impl Default for DoganaTestOptions {
@@ -27,6 +75,14 @@ impl Default for DoganaTestOptions {
             shell: Shell::default(),
             keep_old_containers: false,
             accepted_exit_codes: AcceptExitCode::Success,
+            output_filters: vec![],
+            empty_env: false,
+            timeout: None,
+            resource_limits: ResourceLimits::default(),
+            network: NetworkMode::default(),
+            user: None,
+            working_dir: None,
+            tty: None,
         }
     }
 }
@@ -54,3 +110,92 @@ impl Display for Shell {
         )
     }
 }
+
+/// The network the test container is connected to.
+/// Defaults to `None`, so that tests are hermetic and cannot reach the internet by accident.
+#[derive(Debug, Clone, Default)]
+pub enum NetworkMode {
+    /// The container has only a loopback interface.
+    #[default]
+    None,
+    /// The default bridge network of the container manager, which usually allows to reach the
+    /// internet.
+    Bridge,
+    /// A user-defined network, which must already exist.
+    Named(String),
+}
+
+impl Display for NetworkMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::None => "none",
+                Self::Bridge => "bridge",
+                Self::Named(name) => name,
+            }
+        )
+    }
+}
+
+/// The value of the `TERM` environment variable in TTY mode.
+pub const TERM: &str = "xterm-256color";
+
+/// The size of the pseudo-terminal of a test in TTY mode.
+///
+/// Besides setting the size of the terminal, the `COLUMNS`, `LINES` and `TERM` environment
+/// variables are set, unless they are set explicitly for the test.
+#[derive(Debug, Clone, Copy)]
+pub struct TerminalSize {
+    pub columns: u16,
+    pub rows: u16,
+}
+
+impl TerminalSize {
+    pub(crate) fn env(&self) -> [(&'static str, String); 3] {
+        [
+            ("COLUMNS", self.columns.to_string()),
+            ("LINES", self.rows.to_string()),
+            ("TERM", TERM.to_owned()),
+        ]
+    }
+}
+
+impl Default for TerminalSize {
+    fn default() -> Self {
+        TerminalSize {
+            columns: 80,
+            rows: 24,
+        }
+    }
+}
+
+/// Parse a duration made of an integer and a unit among `ms`, `s`, `m` and `h` (e.g. `30s`).
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let unit_index = text.find(|c: char| !c.is_ascii_digit())?;
+    let value = text[..unit_index].parse::<u64>().ok()?;
+    match &text[unit_index..] {
+        "ms" => Some(Duration::from_millis(value)),
+        "s" => Some(Duration::from_secs(value)),
+        "m" => Some(Duration::from_secs(value * 60)),
+        "h" => Some(Duration::from_secs(value * 60 * 60)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_is_parsed_with_its_unit() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("30"), None);
+        assert_eq!(parse_duration("s"), None);
+        assert_eq!(parse_duration("1d"), None);
+    }
+}
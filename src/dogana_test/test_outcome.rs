@@ -0,0 +1,163 @@
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{exit_status::ExitStatus, transcript::CommandOutcome};
+
+/// The result of the execution of a [DoganaTest](super::DoganaTest) container.
+///
+/// The outcome is returned both when the test passes and, wrapped in an
+/// [AssertionFailure](super::AssertionFailure), when one of its expectations is not met.
+#[derive(Debug, Clone)]
+pub struct TestOutcome {
+    /// The whole standard output of the test script.
+    pub stdout: String,
+    /// The whole standard error of the test script.
+    pub stderr: String,
+    /// The standard output of the init phase, i.e. the output of the init commands.
+    pub init_output: String,
+    /// The standard output of the run phase, i.e. the output of the run commands.
+    pub run_output: String,
+    /// The standard error of the init phase.
+    pub init_stderr: String,
+    /// The standard error of the run phase.
+    pub run_stderr: String,
+    /// The outcomes of the commands which terminated, in transcript mode. In transcript mode, the
+    /// run output and stderr contain the delimiters of the commands.
+    pub commands: Vec<CommandOutcome>,
+    /// How the test container terminated.
+    pub exit_status: ExitStatus,
+    /// Whether the container has been killed as it exceeded its memory limit. It is detected only
+    /// when a memory limit is set.
+    pub oom_killed: bool,
+    /// The time spent running the container.
+    pub duration: Duration,
+    /// The name of the container which ran the test.
+    pub container_name: String,
+    /// The container manager command which ran the test, which can be used to reproduce it.
+    pub command: String,
+    /// The host directory containing the artifacts collected from the container, if any. Each
+    /// artifact is stored at its container path, relative to this directory.
+    pub artifacts_dir: Option<PathBuf>,
+}
+
+impl TestOutcome {
+    /// The host path of a collected artifact, given its container path.
+    ///
+    /// The path is returned only if it has been collected, i.e. if it or one of its parent
+    /// directories has been requested with
+    /// [collect_artifact](super::builder::DoganaTestBuilder::collect_artifact) and it exists.
+    pub fn artifact_path(&self, container_path: &str) -> Option<PathBuf> {
+        self.artifacts_dir
+            .as_ref()
+            .map(|it| it.join(container_path.trim_start_matches('/')))
+            .filter(|it| it.exists())
+    }
+
+    /// The content of a collected file, given its container path.
+    pub fn file(&self, container_path: &str) -> Result<Vec<u8>, IoError> {
+        fs::read(self.existing_artifact_path(container_path)?)
+    }
+
+    /// The content of a collected UTF-8 file, given its container path.
+    pub fn file_to_string(&self, container_path: &str) -> Result<String, IoError> {
+        fs::read_to_string(self.existing_artifact_path(container_path)?)
+    }
+
+    /// The paths of the files and directories of a collected directory, given its container path.
+    /// The paths are relative to the directory and sorted.
+    pub fn directory(&self, container_path: &str) -> Result<Vec<PathBuf>, IoError> {
+        let root = self.existing_artifact_path(container_path)?;
+        let mut entries = vec![];
+        list_directory(&root, &root, &mut entries)?;
+        entries.sort();
+        Ok(entries)
+    }
+
+    fn existing_artifact_path(&self, container_path: &str) -> Result<PathBuf, IoError> {
+        self.artifact_path(container_path).ok_or_else(|| {
+            IoError::new(
+                ErrorKind::NotFound,
+                format!("artifact {} has not been collected", container_path),
+            )
+        })
+    }
+}
+
+fn list_directory(root: &Path, dir: &Path, entries: &mut Vec<PathBuf>) -> Result<(), IoError> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        entries.push(
+            path.strip_prefix(root)
+                .expect("directory entries are inside the root")
+                .to_path_buf(),
+        );
+        if entry.file_type()?.is_dir() {
+            list_directory(root, &path, entries)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn outcome_with_artifacts() -> TestOutcome {
+        let artifacts_dir =
+            std::env::temp_dir().join(format!("dogana-artifacts-{}", Uuid::new_v4()));
+        fs::create_dir_all(artifacts_dir.join("out/nested")).unwrap();
+        fs::write(artifacts_dir.join("out/report.json"), "{}").unwrap();
+        fs::write(artifacts_dir.join("out/nested/data"), [0u8, 1, 2]).unwrap();
+        TestOutcome {
+            stdout: String::new(),
+            stderr: String::new(),
+            init_output: String::new(),
+            run_output: String::new(),
+            init_stderr: String::new(),
+            run_stderr: String::new(),
+            commands: vec![],
+            exit_status: ExitStatus::Exited(0),
+            oom_killed: false,
+            duration: Duration::ZERO,
+            container_name: "container".to_owned(),
+            command: "podman run".to_owned(),
+            artifacts_dir: Some(artifacts_dir),
+        }
+    }
+
+    #[test]
+    fn collected_files_can_be_read() {
+        let outcome = outcome_with_artifacts();
+        assert_eq!(outcome.file_to_string("/out/report.json").unwrap(), "{}");
+        assert_eq!(outcome.file("/out/nested/data").unwrap(), [0u8, 1, 2]);
+    }
+
+    #[test]
+    fn missing_artifact_is_not_found() {
+        let outcome = outcome_with_artifacts();
+        assert_eq!(
+            outcome.file("/out/missing").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn directory_lists_all_entries() {
+        let outcome = outcome_with_artifacts();
+        assert_eq!(
+            outcome.directory("/out").unwrap(),
+            [
+                PathBuf::from("nested"),
+                PathBuf::from("nested/data"),
+                PathBuf::from("report.json")
+            ]
+        );
+    }
+}
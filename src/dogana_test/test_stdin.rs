@@ -0,0 +1,10 @@
+use std::path::PathBuf;
+
+/// The data provided as stdin to the run phase of a test.
+#[derive(Debug, Clone)]
+pub(crate) enum TestStdin {
+    /// The data is written in a temporary file before running the test.
+    Bytes(Vec<u8>),
+    /// The data is read from a file of the host.
+    File(PathBuf),
+}
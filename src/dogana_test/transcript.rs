@@ -0,0 +1,250 @@
+use super::{
+    accept_exit_code::AcceptExitCode, exit_status::ExitStatus, output_matcher::OutputMatcher,
+};
+
+/// The delimiter written after each command of a transcript, followed by its exit code.
+const COMMAND_DELIMITER: &str = "===== COMMAND TERMINATED";
+
+/// A run command of a transcript, with its own expectations.
+///
+/// In transcript mode, the run commands are still run by a single test script, thus they share
+/// the shell state (e.g. the working directory and the variables), but the output and the exit
+/// code of each command are checked separately.
+#[derive(Debug, Clone)]
+pub struct TranscriptStep {
+    /// The shell command.
+    pub command: String,
+    /// The expected standard output of the command, if it is checked.
+    pub expected_output: Option<OutputMatcher>,
+    /// The expected standard error of the command, if it is checked.
+    pub expected_stderr: Option<OutputMatcher>,
+    /// See [AcceptExitCode] for more details.
+    pub accepted_exit_codes: AcceptExitCode,
+}
+
+impl TranscriptStep {
+    /// Create a step which expects the given output and a successful exit code. A string slice is
+    /// matched exactly, see [OutputMatcher] for the other matching policies.
+    pub fn new(command: &str, expected_output: impl Into<OutputMatcher>) -> Self {
+        TranscriptStep {
+            command: command.to_owned(),
+            expected_output: Some(expected_output.into()),
+            expected_stderr: None,
+            accepted_exit_codes: AcceptExitCode::Success,
+        }
+    }
+
+    /// The script which runs the command and then writes its delimiter. In TTY mode, stderr is
+    /// the same stream as stdout, thus the delimiter is not written on stderr.
+    pub(crate) fn script(&self, stderr_delimiter: bool) -> String {
+        // The delimiter is preceded by a newline, in case the output does not end with one.
+        let delimiter = format!(
+            "printf '\\n%s: %s =====\\n' '{}' \"$__dogana_status\"",
+            COMMAND_DELIMITER
+        );
+        let mut script = format!("{}\n__dogana_status=$?\n{}\n", self.command, delimiter);
+        if stderr_delimiter {
+            script.push_str(&format!("{} >&2\n", delimiter));
+        }
+        script
+    }
+
+    /// Check the outcome of the command, if it terminated, returning a description of the
+    /// failure if the expectations are not met.
+    fn check(&self, outcome: Option<&CommandOutcome>) -> Result<(), String> {
+        let Some(outcome) = outcome else {
+            return Err(
+                "the command did not terminate, as the test script exited before or its exit status could not be read"
+                    .to_owned(),
+            );
+        };
+        if !self.accepted_exit_codes.accepts(outcome.exit_status) {
+            return Err(format!(
+                "{} is not accepted by {:?}",
+                outcome.exit_status, self.accepted_exit_codes
+            ));
+        }
+        if let Some(expected_output) = &self.expected_output {
+            expected_output
+                .check(&outcome.stdout)
+                .map_err(|e| format!("output does not match the expected one: {}", e))?;
+        }
+        if let Some(expected_stderr) = &self.expected_stderr {
+            expected_stderr
+                .check(&outcome.stderr)
+                .map_err(|e| format!("stderr does not match the expected one: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// The result of a command of a transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutcome {
+    /// The shell command.
+    pub command: String,
+    /// The standard output of the command.
+    pub stdout: String,
+    /// The standard error of the command. It is empty in TTY mode.
+    pub stderr: String,
+    /// How the command terminated.
+    pub exit_status: ExitStatus,
+}
+
+/// Split the outputs of the run phase into the outcomes of the commands which terminated.
+pub(crate) fn split_commands(
+    steps: &[TranscriptStep],
+    run_output: &str,
+    run_stderr: &str,
+) -> Vec<CommandOutcome> {
+    let mut stderr_segments = split_stream(run_stderr).into_iter();
+    steps
+        .iter()
+        .zip(split_stream(run_output))
+        .map(|(step, (stdout, exit_status))| CommandOutcome {
+            command: step.command.clone(),
+            stdout,
+            stderr: stderr_segments
+                .next()
+                .map(|(stderr, _)| stderr)
+                .unwrap_or_default(),
+            exit_status,
+        })
+        .collect()
+}
+
+/// Check the outcomes of the commands, returning a description of the first failed step, if
+/// any.
+pub(crate) fn check_transcript(
+    steps: &[TranscriptStep],
+    commands: &[CommandOutcome],
+) -> Option<String> {
+    steps
+        .iter()
+        .enumerate()
+        .find_map(|(index, step)| match step.check(commands.get(index)) {
+            Ok(()) => None,
+            Err(reason) => Some(format!(
+                "command {} of {} `{}`: {}",
+                index + 1,
+                steps.len(),
+                step.command,
+                reason
+            )),
+        })
+}
+
+/// Split an output stream of the run phase at the command delimiters, returning the output and
+/// the exit status of each terminated command. The split stops at a delimiter whose exit status
+/// cannot be read, thus its command is considered as not terminated.
+fn split_stream(output: &str) -> Vec<(String, ExitStatus)> {
+    let marker = format!("\n{}: ", COMMAND_DELIMITER);
+    let mut segments = vec![];
+    let mut rest = output;
+    while let Some(index) = rest.find(&marker) {
+        let (line, next) = rest[index + marker.len()..]
+            .split_once('\n')
+            .unwrap_or((&rest[index + marker.len()..], ""));
+        let Some(exit_code) = line
+            .strip_suffix(" =====")
+            .and_then(|it| it.parse::<u8>().ok())
+        else {
+            break;
+        };
+        // The output is compared without its trailing newline, as the whole run output.
+        let segment = &rest[..index];
+        segments.push((
+            segment.strip_suffix('\n').unwrap_or(segment).to_owned(),
+            ExitStatus::from(exit_code),
+        ));
+        rest = next;
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+
+    fn steps() -> Vec<TranscriptStep> {
+        let mut failing = TranscriptStep::new("echo err >&2; false", "");
+        failing.accepted_exit_codes = AcceptExitCode::Specific(1);
+        failing.expected_stderr = Some("err".into());
+        vec![
+            TranscriptStep::new("printf 'no newline'", "no newline"),
+            TranscriptStep::new("true", ""),
+            failing,
+            TranscriptStep::new("echo last", "last"),
+        ]
+    }
+
+    /// Run the transcript script with the local shell, returning the outputs after the init
+    /// phase, as they are extracted from the container.
+    fn run_steps(steps: &[TranscriptStep]) -> (String, String) {
+        let script = steps.iter().map(|it| it.script(true)).collect::<String>();
+        let output = Command::new("sh").args(["-c", &script]).output().unwrap();
+        let joined_lines = |it: &[u8]| {
+            String::from_utf8_lossy(it)
+                .lines()
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        (joined_lines(&output.stdout), joined_lines(&output.stderr))
+    }
+
+    #[test]
+    fn commands_are_split_with_their_exit_status() {
+        let steps = steps();
+        let (stdout, stderr) = run_steps(&steps);
+        let commands = split_commands(&steps, &stdout, &stderr);
+        let outputs = commands
+            .iter()
+            .map(|it| (it.stdout.as_str(), it.stderr.as_str(), it.exit_status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outputs,
+            [
+                ("no newline", "", ExitStatus::Exited(0)),
+                ("", "", ExitStatus::Exited(0)),
+                ("", "err", ExitStatus::Exited(1)),
+                ("last", "", ExitStatus::Exited(0)),
+            ]
+        );
+        assert_eq!(check_transcript(&steps, &commands), None);
+    }
+
+    #[test]
+    fn failure_points_at_the_diverging_command() {
+        let mut steps = steps();
+        steps[3] = TranscriptStep::new("echo other", "last");
+        let (stdout, stderr) = run_steps(&steps);
+        let failure = check_transcript(&steps, &split_commands(&steps, &stdout, &stderr)).unwrap();
+        assert!(
+            failure.starts_with("command 4 of 4 `echo other`: output does not match"),
+            "{}",
+            failure
+        );
+    }
+
+    #[test]
+    fn commands_after_an_exit_did_not_terminate() {
+        let mut steps = steps();
+        steps[1] = TranscriptStep::new("exit 3", "");
+        let (stdout, stderr) = run_steps(&steps);
+        let commands = split_commands(&steps, &stdout, &stderr);
+        assert_eq!(commands.len(), 1);
+        let failure = check_transcript(&steps, &commands).unwrap();
+        assert!(failure.contains("command 2 of 4 `exit 3`: the command did not terminate"));
+    }
+
+    #[test]
+    fn unreadable_delimiter_is_not_a_success() {
+        let steps = [TranscriptStep::new("false", "")];
+        let output = format!("\n{}: <N> =====", COMMAND_DELIMITER);
+        let commands = split_commands(&steps, &output, "");
+        assert!(commands.is_empty());
+        assert!(check_transcript(&steps, &commands).is_some());
+    }
+}
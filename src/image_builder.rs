@@ -6,10 +6,11 @@ use std::io::Error as IoError;
 
 use crate::{
     container_manager::CONTAINER_MANAGER,
+    dogana_images::{TEST_USER, TEST_USER_ID},
     image_name::ImageName,
     metadata::{
-        dogana_metadata::ImageVariant, package_bins, package_msrv, package_name, package_version,
-        required_system_packages,
+        create_test_user, dogana_metadata::ImageVariant, package_bins, package_msrv, package_name,
+        package_version, required_system_packages,
     },
 };
 
@@ -64,10 +65,12 @@ pub trait ImageBuilder {
         FROM {} AS {RUN_STAGE}
         {}
         {}
+        {}
         ",
         self.build_stage_base_image(),
         self.run_stage_base_image(),
         install_system_packages_instruction(self.variant()),
+        create_test_user_instruction(self.variant()),
         copy_bins_instruction(),
         };
         std::fs::write(&tmp_dockerfile_path, dockerfile_content)?;
@@ -114,6 +117,17 @@ fn install_system_packages_instruction(variant: ImageVariant) -> String {
     }
 }
 
+fn create_test_user_instruction(variant: ImageVariant) -> String {
+    if create_test_user(variant) {
+        format!(
+            "RUN {}",
+            variant.create_user_instruction(TEST_USER, TEST_USER_ID)
+        )
+    } else {
+        String::new()
+    }
+}
+
 fn copy_bins_instruction() -> String {
     let generated_bins = package_bins();
     if generated_bins.is_empty() {
@@ -27,8 +27,14 @@
 //!     [package.metadata.dogana.<variant>]
 //!     required_packages = []
 //!     ```
-//!     Each system package is a string that can be installed by the system package manager (e.g.
-//!     apt-get for debian derivatives, or apk for alpine).
+//!   Each system package is a string that can be installed by the system package manager (e.g.
+//!   apt-get for debian derivatives, or apk for alpine).
+//! * Whether to create an unprivileged test user in the images, categorized by image variants, in
+//!   the same metadata section:
+//!     ```toml
+//!     [package.metadata.dogana.<variant>]
+//!     create_test_user = true
+//!     ```
 //!
 //! Each supported image defines a metadata key. You can see all the supported images in
 //! [dogana_images].
@@ -58,7 +64,26 @@
 //!
 //! As you can see, thanks to the possibility of expliciting a return value for test methods,
 //! Dogana tests can be very concise.
+//!
+//! The [test] attribute removes the remaining boilerplate: it turns a function returning the
+//! builder into a test, named after the function, for one or more image variants:
+//! ```ignore
+//! use dogana::dogana_test::builder::DoganaTestBuilder;
+//!
+//! #[dogana::test(image = [debian, alpine], timeout = "30s")]
+//! fn basic_integration_test() -> DoganaTestBuilder {
+//!     let mut builder = DoganaTestBuilder::new();
+//!     builder
+//!         .set_run_commands(&["echo \"test\""])
+//!         .set_expected_output("test");
+//!     builder
+//! }
+//! ```
+//!
+//! Tests can also be written as console sessions in `.console` or Markdown files, see
+//! [console_test].
 
+pub mod console_test;
 mod container_manager;
 pub mod dogana_images;
 pub mod dogana_test;
@@ -66,3 +91,5 @@ mod image_builder;
 mod image_builder_factory;
 pub mod image_name;
 mod metadata;
+
+pub use dogana_macros::test;
@@ -77,3 +77,12 @@ pub fn required_system_packages(variant: ImageVariant) -> Vec<String> {
         .and_then(|it| it.required_packages.clone())
         .unwrap_or_default()
 }
+
+pub fn create_test_user(variant: ImageVariant) -> bool {
+    DOGANA_METADATA
+        .as_ref()
+        .and_then(|it| it.dogana.as_ref())
+        .and_then(|it| it.get(&variant))
+        .and_then(|it| it.create_test_user)
+        .unwrap_or_default()
+}
@@ -2,6 +2,8 @@ use std::{collections::HashMap, fmt::Display};
 
 use serde::Deserialize;
 
+/// An image variant, i.e. a base distribution of the Dogana images. It is displayed as its
+/// metadata key.
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ImageVariant {
@@ -10,7 +12,7 @@ pub enum ImageVariant {
 }
 
 impl ImageVariant {
-    pub fn install_sys_package_instruction(&self, packages: &[String]) -> String {
+    pub(crate) fn install_sys_package_instruction(&self, packages: &[String]) -> String {
         let pkgs = packages.join(" ");
         format!(
             "{} {}",
@@ -21,6 +23,13 @@ impl ImageVariant {
             pkgs
         )
     }
+
+    pub(crate) fn create_user_instruction(&self, user: &str, uid: u32) -> String {
+        match self {
+            Self::Alpine => format!("adduser -D -u {} {}", uid, user),
+            Self::Debian => format!("useradd --create-home --user-group --uid {} {}", uid, user),
+        }
+    }
 }
 
 impl Display for ImageVariant {
@@ -44,4 +53,5 @@ pub struct DoganaMetadata {
 #[derive(Deserialize)]
 pub struct VariantMetadata {
     pub required_packages: Option<Vec<String>>,
+    pub create_test_user: Option<bool>,
 }
@@ -1,6 +1,9 @@
 use dogana::{
-    dogana_images::DEBIAN_IMAGE,
-    dogana_test::{builder::DoganaTestBuilder, test_options::DoganaTestOptions, DoganaTestResult},
+    dogana_images::{ImageVariant, DEBIAN_IMAGE},
+    dogana_test::{
+        builder::DoganaTestBuilder, matrix::DoganaTestMatrixResult,
+        test_options::DoganaTestOptions, DoganaTestResult,
+    },
 };
 
 #[test]
@@ -16,3 +19,24 @@ fn basic_integration_test() -> DoganaTestResult {
         .build()
         .run()
 }
+
+#[dogana::test(image = debian, timeout = "1m")]
+fn macro_integration_test() -> DoganaTestBuilder {
+    let mut builder = DoganaTestBuilder::new();
+    builder
+        .set_run_commands(&["echo \"test\""])
+        .set_expected_output("test");
+    builder
+}
+
+#[test]
+fn matrix_integration_test() -> DoganaTestMatrixResult {
+    DoganaTestBuilder::new()
+        .set_base_images(&[ImageVariant::Debian, ImageVariant::Alpine])
+        .set_parallel_variants(true)
+        .set_cross_variant_check(true)
+        .set_run_commands(&["echo \"test\""])
+        .set_expected_output("test")
+        .build_matrix()
+        .run()
+}